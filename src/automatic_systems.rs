@@ -1,15 +1,28 @@
 use std::marker::PhantomData;
 
 use crate::{
-    point::{SpatialPoint, VecFromGlobalTransform, VecFromTransform},
+    kdtree::AsyncRebuild,
+    neighbours::update_neighbours,
+    plugin::BuildFn,
+    point::{
+        SamplePoints, SpatialCoordinate, SpatialGridCell, SpatialPoint, SpatialTilePosition,
+        VecFromCoordinate, VecFromGlobalTransform, VecFromTilePosition, VecFromTransform,
+    },
     spatial_access::UpdateSpatialAccess,
     SpatialAccess,
 };
 
 use bevy::{
-    ecs::schedule::{ScheduleLabel, SystemSet},
+    ecs::{
+        entity::EntityHashSet,
+        query::{QueryFilter, ROQueryItem, ReadOnlyQueryData},
+        schedule::{InternedScheduleLabel, InternedSystemSet},
+    },
+    math::DVec3,
     prelude::*,
 };
+use num_traits::FromPrimitive;
+use smallvec::{smallvec, SmallVec};
 
 /// Select which Transform to use when automatically updating the Spatial Datastructure.
 #[derive(Clone, Default, Copy)]
@@ -19,71 +32,242 @@ pub enum TransformMode {
     Transform,
     /// Uses the [`GlobalTransform`] for updating the Spatial Datastructure.
     GlobalTransform,
+    /// Uses a component implementing [`SpatialCoordinate`], [`SpatialGridCell`] or [`SpatialTilePosition`] for updating the Spatial Datastructure.
+    ///
+    /// Set together with the component by [`AutomaticUpdate::with_custom_coordinate`](crate::AutomaticUpdate::with_custom_coordinate),
+    /// [`AutomaticUpdate::with_grid_cell`](crate::AutomaticUpdate::with_grid_cell)
+    /// or [`AutomaticUpdate::with_tile_position`](crate::AutomaticUpdate::with_tile_position).
+    Custom(CustomCoordinate),
 }
-// long term todo: add support for CustomCoordinate mode which uses a user-defined type implementing a trait like VecFromTransform
 
-type GlamVec<S> = <<S as SpatialAccess>::Point as SpatialPoint>::Vec;
+/// The component [`TransformMode::Custom`] takes coordinates from.
+///
+/// Can't be created directly, as only the methods of [`AutomaticUpdate`](crate::AutomaticUpdate)
+/// setting [`TransformMode::Custom`] know the type of the component.
+#[derive(Clone, Copy)]
+pub struct CustomCoordinate(pub(crate) BuildFn);
 
-pub(crate) struct AutoT<SpatialDS>(PhantomData<SpatialDS>);
+pub(crate) type Scalar<S> = <<S as SpatialAccess>::Point as SpatialPoint>::Scalar;
+pub(crate) type GlamVec<S> = <<S as SpatialAccess>::Point as SpatialPoint>::Vec;
 
-impl<SpatialDS> AutoT<SpatialDS>
-where
-    GlamVec<SpatialDS>: VecFromTransform,
+/// Entities which lost their [`SamplePoints`] since the last update of `SpatialDS`, so their own location is tracked again.
+#[derive(Resource)]
+struct LostSamples<SpatialDS>(EntityHashSet, PhantomData<SpatialDS>);
+
+impl<SpatialDS> Default for LostSamples<SpatialDS> {
+    fn default() -> Self {
+        Self(default(), PhantomData)
+    }
+}
+
+/// Removes entities which lost their marker component or were despawned,
+/// and remembers the entities which lost their [`SamplePoints`] for the next update.
+///
+/// Runs every frame instead of only on update ticks, as removal events are only kept around for two frames.
+#[allow(clippy::needless_pass_by_value)]
+fn remove_ds<SpatialDS>(
+    mut tree: ResMut<SpatialDS>,
+    mut removed: RemovedComponents<SpatialDS::Comp>,
+    mut removed_samples: RemovedComponents<SamplePoints>,
+    mut lost_samples: ResMut<LostSamples<SpatialDS>>,
+) where
     SpatialDS: UpdateSpatialAccess + Resource,
-    <SpatialDS as SpatialAccess>::Point: From<(Entity, GlamVec<SpatialDS>)>,
-    SpatialDS::Comp: Component,
 {
-    #[allow(clippy::needless_pass_by_value)]
-    fn update_ds(
-        mut tree: ResMut<SpatialDS>,
-        changed: Query<(Entity, Ref<Transform>), With<SpatialDS::Comp>>,
-        mut removed: RemovedComponents<SpatialDS::Comp>,
-    ) {
-        tree.update(
-            changed.iter().map(|(e, ch)| {
-                let changed = ch.is_changed();
-                (
-                    (e, GlamVec::<SpatialDS>::from_transform(ch.into_inner())).into(),
-                    changed,
-                )
-            }),
-            removed.read(),
-        );
-    }
-
-    pub fn build(app: &mut App, schedule: impl ScheduleLabel, set: impl SystemSet) {
-        app.add_systems(schedule, Self::update_ds.in_set(set));
+    for entity in removed.read() {
+        tree.remove_entity(entity);
     }
+    lost_samples.0.extend(removed_samples.read());
 }
 
-pub(crate) struct AutoGT<SpatialDS>(PhantomData<SpatialDS>);
-
-impl<SpatialDS> AutoGT<SpatialDS>
+/// The locations tracked for an entity: each of its [`SamplePoints`] placed by `Source`, or its own location without any.
+fn sample_locations<Source, V>(
+    location: &ROQueryItem<'_, Source::Location>,
+    samples: Option<&SamplePoints>,
+) -> SmallVec<[V; 1]>
 where
-    GlamVec<SpatialDS>: VecFromGlobalTransform,
+    Source: CoordinateSource,
+    V: VecFromTransform + VecFromGlobalTransform + VecFromCoordinate + VecFromTilePosition,
+{
+    match samples {
+        Some(SamplePoints(points)) if !points.is_empty() => points
+            .iter()
+            .map(|&p| V::from_coordinate(Source::sample(location, p)))
+            .collect(),
+        _ => smallvec![Source::location(location)],
+    }
+}
+
+/// Swaps in trees which finished building in the background, runs every frame.
+///
+/// Bypasses change detection unless a new tree was swapped in.
+pub(crate) fn poll_rebuild<SpatialDS: AsyncRebuild>(mut tree: ResMut<SpatialDS>) {
+    if tree.bypass_change_detection().poll_rebuild() {
+        tree.set_changed();
+    }
+}
+
+/// Where [`AutomaticUpdate`](crate::AutomaticUpdate) takes the coordinates of tracked entities from.
+pub(crate) trait CoordinateSource: Sized + 'static {
+    /// The components the location of an entity is read from.
+    type Location: ReadOnlyQueryData + 'static;
+    /// Matches the entities whose [`Location`](Self::Location) changed since the last update.
+    type Changed: QueryFilter + 'static;
+
+    /// Read the location of an entity from its [`Location`](Self::Location) components.
+    fn location<V>(item: &ROQueryItem<'_, Self::Location>) -> V
+    where
+        V: VecFromTransform + VecFromGlobalTransform + VecFromCoordinate + VecFromTilePosition;
+
+    /// Place a point of [`SamplePoints`], given relative to the entity, in the world.
+    fn sample(item: &ROQueryItem<'_, Self::Location>, offset: Vec3) -> DVec3;
+
+    /// Add the systems which keep `SpatialDS` updated from this source.
+    fn build<SpatialDS>(app: &mut App, schedule: InternedScheduleLabel, set: InternedSystemSet)
+    where
+        SpatialDS: UpdateSpatialAccess<ResultT = (GlamVec<SpatialDS>, Option<Entity>)> + Resource,
+        GlamVec<SpatialDS>:
+            VecFromTransform + VecFromGlobalTransform + VecFromCoordinate + VecFromTilePosition,
+        <SpatialDS as SpatialAccess>::Point: From<(Entity, GlamVec<SpatialDS>)>,
+        Scalar<SpatialDS>: FromPrimitive,
+    {
+        app.add_systems(
+            schedule,
+            (
+                update_ds::<Self, SpatialDS>,
+                update_neighbours::<Self, SpatialDS>.run_if(resource_changed::<SpatialDS>),
+            )
+                .chain()
+                .in_set(set),
+        )
+        .add_systems(schedule, remove_ds::<SpatialDS>.before(set))
+        .init_resource::<LostSamples<SpatialDS>>();
+    }
+}
+
+/// Updates the datastructure with the locations of all tracked entities, read from `Source`.
+#[allow(clippy::needless_pass_by_value, clippy::type_complexity)]
+fn update_ds<Source, SpatialDS>(
+    mut tree: ResMut<SpatialDS>,
+    tracked: Query<(
+        Entity,
+        Source::Location,
+        Ref<SpatialDS::Comp>,
+        Option<Ref<SamplePoints>>,
+    )>,
+    changed: Query<(), Source::Changed>,
+    mut lost_samples: ResMut<LostSamples<SpatialDS>>,
+) where
+    Source: CoordinateSource,
     SpatialDS: UpdateSpatialAccess + Resource,
+    GlamVec<SpatialDS>:
+        VecFromTransform + VecFromGlobalTransform + VecFromCoordinate + VecFromTilePosition,
     <SpatialDS as SpatialAccess>::Point: From<(Entity, GlamVec<SpatialDS>)>,
-    SpatialDS::Comp: Component,
 {
-    #[allow(clippy::needless_pass_by_value)]
-    fn update_ds(
-        mut tree: ResMut<SpatialDS>,
-        changed: Query<(Entity, Ref<GlobalTransform>), With<SpatialDS::Comp>>,
-        mut removed: RemovedComponents<SpatialDS::Comp>,
-    ) {
-        tree.update(
-            changed.iter().map(|(e, ch)| {
-                let changed = ch.is_changed();
-                (
-                    (e, GlamVec::<SpatialDS>::from_transform(ch.into_inner())).into(),
-                    changed,
-                )
-            }),
-            removed.read(),
-        );
-    }
-
-    pub fn build(app: &mut App, schedule: impl ScheduleLabel, set: impl SystemSet) {
-        app.add_systems(schedule, Self::update_ds.in_set(set));
+    let lost_samples = std::mem::take(&mut lost_samples.0);
+    // only mark the datastructure as changed if queries can see the update, so systems running on changes don't run every tick
+    let updated = tree.bypass_change_detection().update(
+        tracked.iter().flat_map(|(e, location, marker, samples)| {
+            let is_changed = changed.contains(e)
+                || marker.is_added()
+                || samples.as_ref().is_some_and(DetectChanges::is_changed)
+                || lost_samples.contains(&e);
+            sample_locations::<Source, _>(&location, samples.as_deref())
+                .into_iter()
+                .map(move |v| ((e, v).into(), is_changed))
+        }),
+        std::iter::empty(),
+    );
+    if updated {
+        tree.set_changed();
+    }
+}
+
+pub(crate) struct AutoT;
+
+impl CoordinateSource for AutoT {
+    type Location = &'static Transform;
+    type Changed = Changed<Transform>;
+
+    fn location<V>(t: &&Transform) -> V
+    where
+        V: VecFromTransform + VecFromGlobalTransform + VecFromCoordinate + VecFromTilePosition,
+    {
+        <V as VecFromTransform>::from_transform(t)
+    }
+
+    fn sample(t: &&Transform, offset: Vec3) -> DVec3 {
+        t.transform_point(offset).as_dvec3()
+    }
+}
+
+pub(crate) struct AutoGT;
+
+impl CoordinateSource for AutoGT {
+    type Location = &'static GlobalTransform;
+    type Changed = Changed<GlobalTransform>;
+
+    fn location<V>(t: &&GlobalTransform) -> V
+    where
+        V: VecFromTransform + VecFromGlobalTransform + VecFromCoordinate + VecFromTilePosition,
+    {
+        <V as VecFromGlobalTransform>::from_transform(t)
+    }
+
+    fn sample(t: &&GlobalTransform, offset: Vec3) -> DVec3 {
+        t.transform_point(offset).as_dvec3()
+    }
+}
+
+pub(crate) struct AutoC<Coord>(PhantomData<Coord>);
+
+impl<Coord: SpatialCoordinate> CoordinateSource for AutoC<Coord> {
+    type Location = &'static Coord;
+    type Changed = Changed<Coord>;
+
+    fn location<V>(coord: &&Coord) -> V
+    where
+        V: VecFromTransform + VecFromGlobalTransform + VecFromCoordinate + VecFromTilePosition,
+    {
+        V::from_coordinate(coord.coordinate())
+    }
+
+    fn sample(coord: &&Coord, offset: Vec3) -> DVec3 {
+        coord.coordinate() + offset.as_dvec3()
+    }
+}
+
+pub(crate) struct AutoCell<Cell>(PhantomData<Cell>);
+
+impl<Cell: SpatialGridCell> CoordinateSource for AutoCell<Cell> {
+    type Location = (&'static Cell, &'static Transform);
+    type Changed = Or<(Changed<Cell>, Changed<Transform>)>;
+
+    fn location<V>((cell, t): &(&Cell, &Transform)) -> V
+    where
+        V: VecFromTransform + VecFromGlobalTransform + VecFromCoordinate + VecFromTilePosition,
+    {
+        V::from_coordinate(cell.cell_origin() + t.translation.as_dvec3())
+    }
+
+    fn sample((cell, t): &(&Cell, &Transform), offset: Vec3) -> DVec3 {
+        cell.cell_origin() + t.transform_point(offset).as_dvec3()
+    }
+}
+
+pub(crate) struct AutoTile<Tile>(PhantomData<Tile>);
+
+impl<Tile: SpatialTilePosition> CoordinateSource for AutoTile<Tile> {
+    type Location = &'static Tile;
+    type Changed = Changed<Tile>;
+
+    fn location<V>(tile: &&Tile) -> V
+    where
+        V: VecFromTransform + VecFromGlobalTransform + VecFromCoordinate + VecFromTilePosition,
+    {
+        V::from_tile_position(tile.tile_position())
+    }
+
+    fn sample(tile: &&Tile, offset: Vec3) -> DVec3 {
+        tile.tile_position().as_dvec3() + offset.as_dvec3()
     }
 }
@@ -0,0 +1,576 @@
+//! Spatial datastructures for entities with a size, indexed by their bounding sphere or box instead of a single point.
+//!
+//! The point based datastructures only know the position of each entity, so a large entity whose center is far away
+//! is not found even when its body is close. The extent trees, like [`ExtentTree3`], index the [`Bounds`] of each entity instead
+//! and measure distances to their surface.
+//!
+//! The size of an entity is taken from a component implementing [`SpatialExtent`],
+//! using the [`AutomaticExtentUpdate`](crate::AutomaticExtentUpdate) plugin with
+//! one of the variants of [`ExtentStructure`](crate::ExtentStructure).
+
+use bevy::{
+    ecs::entity::EntityHashSet,
+    math::{Affine3A, Vec3A},
+    prelude::*,
+};
+use num_traits::Zero;
+use typenum::Unsigned;
+
+use crate::{
+    point::{Scalar, SpatialPoint, VecFromCoordinate},
+    spatial_access::insert_nearest,
+    TComp,
+};
+
+use std::{marker::PhantomData, ops::ControlFlow};
+
+/// The shape of an entity in its local space, moved, rotated and scaled with its transform.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Extent {
+    /// A sphere, which stays a sphere when scaled unevenly by growing to the largest scale.
+    Sphere {
+        /// The center of the sphere, relative to the entity.
+        center: Vec3,
+        /// The radius of the sphere.
+        radius: f32,
+    },
+    /// A box, which is indexed by the axis-aligned box around it after rotating.
+    Cuboid {
+        /// The center of the box, relative to the entity.
+        center: Vec3,
+        /// Half the size of the box along each axis.
+        half_extents: Vec3,
+    },
+}
+
+impl Extent {
+    /// A sphere with `radius` around the entity.
+    #[must_use]
+    pub fn sphere(radius: f32) -> Self {
+        Self::Sphere {
+            center: Vec3::ZERO,
+            radius,
+        }
+    }
+
+    /// A box with `half_extents` around the entity.
+    #[must_use]
+    pub fn cuboid(half_extents: Vec3) -> Self {
+        Self::Cuboid {
+            center: Vec3::ZERO,
+            half_extents,
+        }
+    }
+
+    /// The world space center and half extents after applying `affine`, and whether the shape is a sphere.
+    fn transformed(&self, affine: &Affine3A) -> (Vec3, Vec3, bool) {
+        match *self {
+            Self::Sphere { center, radius } => {
+                let m = affine.matrix3;
+                let scale = m
+                    .x_axis
+                    .length()
+                    .max(m.y_axis.length())
+                    .max(m.z_axis.length());
+                (
+                    affine.transform_point3(center),
+                    Vec3::splat(radius.abs() * scale),
+                    true,
+                )
+            }
+            Self::Cuboid {
+                center,
+                half_extents,
+            } => (
+                affine.transform_point3(center),
+                Vec3::from(affine.matrix3.abs() * Vec3A::from(half_extents.abs())),
+                false,
+            ),
+        }
+    }
+}
+
+/// Trait for components describing the size of their entity, used by [`AutomaticExtentUpdate`](crate::AutomaticExtentUpdate).
+///
+/// Implemented for the [`Aabb`](bevy::render::primitives::Aabb) of meshes with the `bevy_render` feature.
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_spatial::extent::{Extent, SpatialExtent};
+/// #[derive(Component)]
+/// struct HitRadius(f32);
+///
+/// impl SpatialExtent for HitRadius {
+///     fn extent(&self) -> Extent {
+///         Extent::sphere(self.0)
+///     }
+/// }
+/// ```
+pub trait SpatialExtent: Component {
+    /// The shape of this entity in its local space.
+    fn extent(&self) -> Extent;
+}
+
+#[cfg(feature = "bevy_render")]
+impl SpatialExtent for bevy::render::primitives::Aabb {
+    fn extent(&self) -> Extent {
+        Extent::Cuboid {
+            center: self.center.into(),
+            half_extents: self.half_extents.into(),
+        }
+    }
+}
+
+/// The world space bounds of a tracked entity, a sphere or an axis-aligned box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds<V> {
+    /// The center of the bounds.
+    pub center: V,
+    /// Half the size of the box around the bounds along each axis, the radius for spheres.
+    pub half_extents: V,
+    /// Whether the bounds are the sphere inside of the box, instead of the box.
+    pub sphere: bool,
+    /// The entity the bounds belong to.
+    pub entity: Option<Entity>,
+}
+
+impl<V: VecFromCoordinate> Bounds<V> {
+    /// The bounds of `entity` with `extent`, placed in the world by `affine`.
+    #[must_use]
+    pub fn from_extent(entity: Entity, extent: &Extent, affine: &Affine3A) -> Self {
+        let (center, half_extents, sphere) = extent.transformed(affine);
+        Self {
+            center: V::from_coordinate(center.as_dvec3()),
+            half_extents: V::from_coordinate(half_extents.as_dvec3()),
+            sphere,
+            entity: Some(entity),
+        }
+    }
+}
+
+/// Trait for querying spatial datastructures of entities with a size, like [`ExtentTree3`].
+///
+/// Distances are measured to the surface of the [`Bounds`] of each entity, and are zero inside of them.
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_spatial::extent::{ExtentTree3, SpatialExtentAccess};
+/// # #[derive(Component)]
+/// # struct Enemy;
+/// fn explode(tree: Res<ExtentTree3<Enemy>>) {
+///     // finds large enemies whose center is further away than 5.0, but whose body is not
+///     for bounds in tree.within_distance(Vec3::ZERO, 5.0) {
+///         // ...
+///     }
+/// }
+/// # bevy::ecs::system::assert_is_system(explode);
+/// ```
+#[allow(clippy::module_name_repetitions)]
+pub trait SpatialExtentAccess: Send + Sync + 'static {
+    /// The vector type of positions, like [`Vec3`].
+    type Vec: Copy;
+    /// The scalar type of distances, like [`f32`].
+    type Scalar: Scalar;
+    /// The marker component of the tracked entities.
+    type Comp: TComp;
+
+    /// Get the bounds closest to `loc`, together with their squared distance.
+    fn nearest_neighbour(&self, loc: Self::Vec) -> Option<(Self::Scalar, Bounds<Self::Vec>)> {
+        self.k_nearest_neighbour(loc, 1).into_iter().next()
+    }
+
+    /// Get the `k` bounds closest to `loc`, together with their squared distance.
+    ///
+    /// Results are sorted by distance, closest first.
+    fn k_nearest_neighbour(
+        &self,
+        loc: Self::Vec,
+        k: usize,
+    ) -> Vec<(Self::Scalar, Bounds<Self::Vec>)>;
+
+    /// Get all bounds within `distance` of `loc`, which are all bounds overlapping the sphere with radius `distance` around `loc`.
+    fn within_distance(&self, loc: Self::Vec, distance: Self::Scalar) -> Vec<Bounds<Self::Vec>> {
+        self.within_distance_with_distance(loc, distance)
+            .into_iter()
+            .map(|(_, bounds)| bounds)
+            .collect()
+    }
+
+    /// Get all bounds within `distance` of `loc`, together with their squared distance. Results are in no particular order.
+    fn within_distance_with_distance(
+        &self,
+        loc: Self::Vec,
+        distance: Self::Scalar,
+    ) -> Vec<(Self::Scalar, Bounds<Self::Vec>)>;
+
+    /// Get all bounds containing `loc`, including their boundary.
+    fn containing(&self, loc: Self::Vec) -> Vec<Bounds<Self::Vec>> {
+        self.within_distance(loc, Self::Scalar::zero())
+    }
+
+    /// Get all bounds overlapping the AABB spanned by `min` and `max`, including its boundary.
+    ///
+    /// `min` and `max` can be any two opposite corners of the AABB, the coordinates will be sorted.
+    fn within_aabb(&self, min: Self::Vec, max: Self::Vec) -> Vec<Bounds<Self::Vec>>;
+
+    /// Iterate over the bounds of all tracked entities, in no particular order.
+    fn iter_bounds(&self) -> impl Iterator<Item = &Bounds<Self::Vec>>;
+}
+
+/// Trait for updating spatial datastructures of entities with a size.
+///
+/// Used by [`AutomaticExtentUpdate`](crate::AutomaticExtentUpdate), but can also be used to fill a datastructure manually.
+pub trait UpdateExtentAccess: SpatialExtentAccess {
+    /// Update the datastructure with the bounds of all tracked entities, and whether they changed since the last update.
+    ///
+    /// Returns whether queries can see the update.
+    fn update(&mut self, data: impl Iterator<Item = (Bounds<Self::Vec>, bool)>) -> bool;
+
+    /// Remove the bounds of `entity`.
+    fn remove_entity(&mut self, entity: Entity) -> bool;
+
+    /// Remove all bounds.
+    fn clear(&mut self);
+}
+
+/// Components placing tracked entities in the world, [`Transform`] or [`GlobalTransform`].
+pub(crate) trait ExtentLocation: Component {
+    /// The transformation from the local space of the entity to the world.
+    fn affine(&self) -> Affine3A;
+}
+
+impl ExtentLocation for Transform {
+    fn affine(&self) -> Affine3A {
+        self.compute_affine()
+    }
+}
+
+impl ExtentLocation for GlobalTransform {
+    fn affine(&self) -> Affine3A {
+        GlobalTransform::affine(self)
+    }
+}
+
+/// Updates the extent datastructure with the bounds of all entities with an extent.
+#[allow(clippy::needless_pass_by_value, clippy::type_complexity)]
+pub(crate) fn update_extent_ds<Loc, E, ExtentDS>(
+    mut tree: ResMut<ExtentDS>,
+    changed: Query<(Entity, Ref<Loc>, Ref<E>, Ref<ExtentDS::Comp>)>,
+) where
+    Loc: ExtentLocation,
+    E: SpatialExtent,
+    ExtentDS: UpdateExtentAccess + Resource,
+    ExtentDS::Vec: VecFromCoordinate,
+{
+    let updated = tree.bypass_change_detection().update(changed.iter().map(
+        |(entity, loc, extent, marker)| {
+            let changed = loc.is_changed() || extent.is_changed() || marker.is_added();
+            (
+                Bounds::from_extent(entity, &extent.extent(), &loc.affine()),
+                changed,
+            )
+        },
+    ));
+    if updated {
+        tree.set_changed();
+    }
+}
+
+/// Removes entities which lost their marker or extent component or were despawned.
+///
+/// Runs every frame instead of only on update ticks, as removal events are only kept around for two frames.
+#[allow(clippy::needless_pass_by_value)]
+pub(crate) fn remove_extent_ds<E, ExtentDS>(
+    mut tree: ResMut<ExtentDS>,
+    mut removed: RemovedComponents<ExtentDS::Comp>,
+    mut removed_extents: RemovedComponents<E>,
+) where
+    E: SpatialExtent,
+    ExtentDS: UpdateExtentAccess + Resource,
+{
+    for entity in removed.read().chain(removed_extents.read()) {
+        tree.remove_entity(entity);
+    }
+}
+
+macro_rules! extent_impl {
+    ($pt:ty, $treename:ident) => {
+        impl Bounds<<$pt as SpatialPoint>::Vec> {
+            /// The smallest and largest corner of the box around the bounds.
+            #[must_use]
+            pub fn corners(&self) -> (<$pt as SpatialPoint>::Vec, <$pt as SpatialPoint>::Vec) {
+                (
+                    self.center - self.half_extents,
+                    self.center + self.half_extents,
+                )
+            }
+
+            /// The squared distance from `loc` to the surface of the bounds, zero inside of them.
+            #[must_use]
+            pub fn distance_squared(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+            ) -> <$pt as SpatialPoint>::Scalar {
+                if self.sphere {
+                    let outside = (loc.distance(self.center) - self.half_extents.x).max(0.0);
+                    outside * outside
+                } else {
+                    ((loc - self.center).abs() - self.half_extents)
+                        .max(<$pt as SpatialPoint>::Vec::ZERO)
+                        .length_squared()
+                }
+            }
+
+            /// Whether the bounds overlap the AABB between `min` and `max`, which have to be sorted.
+            #[must_use]
+            pub fn overlaps_aabb(
+                &self,
+                min: <$pt as SpatialPoint>::Vec,
+                max: <$pt as SpatialPoint>::Vec,
+            ) -> bool {
+                if self.sphere {
+                    let closest = self.center.clamp(min, max);
+                    closest.distance_squared(self.center)
+                        <= self.half_extents.x * self.half_extents.x
+                } else {
+                    let (bmin, bmax) = self.corners();
+                    bmin.cmple(max).all() && min.cmple(bmax).all()
+                }
+            }
+        }
+
+        /// Resource for storing the [`Bounds`] of entities with a size.
+        ///
+        /// The bounds are sorted like a KD-tree by their centers, and each subtree keeps the box around all of its bounds,
+        /// so queries can skip subtrees whose box is too far away. Fully rebuilt on every update which changed any bounds.
+        ///
+        /// Removed entities are skipped by queries until the next rebuild drops them.
+        #[derive(Resource)]
+        pub struct $treename<Comp> {
+            bounds: Vec<Bounds<<$pt as SpatialPoint>::Vec>>,
+            /// The box around the subtree whose middle is at the same index in `bounds`.
+            nodes: Vec<(<$pt as SpatialPoint>::Vec, <$pt as SpatialPoint>::Vec)>,
+            /// The bounds collected on the last update, kept to reuse the allocation.
+            buffer: Vec<Bounds<<$pt as SpatialPoint>::Vec>>,
+            /// Entities removed since the last rebuild.
+            removed: EntityHashSet,
+            component_type: PhantomData<Comp>,
+        }
+
+        impl<Comp> Default for $treename<Comp> {
+            fn default() -> Self {
+                Self {
+                    bounds: Vec::new(),
+                    nodes: Vec::new(),
+                    buffer: Vec::new(),
+                    removed: EntityHashSet::default(),
+                    component_type: PhantomData,
+                }
+            }
+        }
+
+        impl<Comp> $treename<Comp> {
+            /// Whether the bounds belong to an entity which was not removed since the last rebuild.
+            fn is_live(&self, bounds: &Bounds<<$pt as SpatialPoint>::Vec>) -> bool {
+                self.removed.is_empty() || bounds.entity.is_none_or(|e| !self.removed.contains(&e))
+            }
+
+            /// Sorts `bounds` by their center along `axis`, alternating the axis in each half,
+            /// and stores the box around them in the middle of `nodes`, which is also returned.
+            fn build_node(
+                bounds: &mut [Bounds<<$pt as SpatialPoint>::Vec>],
+                nodes: &mut [(<$pt as SpatialPoint>::Vec, <$pt as SpatialPoint>::Vec)],
+                axis: usize,
+            ) -> Option<(<$pt as SpatialPoint>::Vec, <$pt as SpatialPoint>::Vec)> {
+                if bounds.is_empty() {
+                    return None;
+                }
+                let mid = bounds.len() / 2;
+                bounds
+                    .select_nth_unstable_by(mid, |a, b| a.center[axis].total_cmp(&b.center[axis]));
+                let next = (axis + 1) % <$pt as SpatialPoint>::Dimension::USIZE;
+
+                let (left, right) = bounds.split_at_mut(mid);
+                let (left_nodes, right_nodes) = nodes.split_at_mut(mid);
+                let mut node = right[0].corners();
+                for (min, max) in [
+                    Self::build_node(left, left_nodes, next),
+                    Self::build_node(&mut right[1..], &mut right_nodes[1..], next),
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    node = (node.0.min(min), node.1.max(max));
+                }
+                right_nodes[0] = node;
+                Some(node)
+            }
+
+            /// Calls `f` for the bounds of every subtree whose box passes `visit`, until `f` breaks.
+            fn try_for_each_node(
+                &self,
+                range: std::ops::Range<usize>,
+                visit: &impl Fn(<$pt as SpatialPoint>::Vec, <$pt as SpatialPoint>::Vec) -> bool,
+                f: &mut impl FnMut(&Bounds<<$pt as SpatialPoint>::Vec>) -> ControlFlow<()>,
+            ) -> ControlFlow<()> {
+                if range.is_empty() {
+                    return ControlFlow::Continue(());
+                }
+                let mid = range.start + range.len() / 2;
+                let (min, max) = self.nodes[mid];
+                if !visit(min, max) {
+                    return ControlFlow::Continue(());
+                }
+                if self.is_live(&self.bounds[mid]) {
+                    f(&self.bounds[mid])?;
+                }
+                self.try_for_each_node(range.start..mid, visit, f)?;
+                self.try_for_each_node(mid + 1..range.end, visit, f)
+            }
+
+            /// Collects the `k` bounds closest to `loc` from a subtree into `found`, skipping subtrees further away than all of them.
+            fn nearest_node(
+                &self,
+                range: std::ops::Range<usize>,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                axis: usize,
+                found: &mut Vec<(
+                    <$pt as SpatialPoint>::Scalar,
+                    Bounds<<$pt as SpatialPoint>::Vec>,
+                )>,
+            ) {
+                if range.is_empty() {
+                    return;
+                }
+                let mid = range.start + range.len() / 2;
+                let (min, max) = self.nodes[mid];
+                let node_distance = loc.clamp(min, max).distance_squared(loc);
+                if found.len() == k && found.last().is_some_and(|(d, _)| *d < node_distance) {
+                    return;
+                }
+                let bounds = self.bounds[mid];
+                if self.is_live(&bounds) {
+                    insert_nearest(found, k, bounds.distance_squared(loc), bounds);
+                }
+
+                let next = (axis + 1) % <$pt as SpatialPoint>::Dimension::USIZE;
+                let (near, far) = if loc[axis] < bounds.center[axis] {
+                    (range.start..mid, mid + 1..range.end)
+                } else {
+                    (mid + 1..range.end, range.start..mid)
+                };
+                self.nearest_node(near, loc, k, next, found);
+                self.nearest_node(far, loc, k, next, found);
+            }
+        }
+
+        impl<Comp: TComp> SpatialExtentAccess for $treename<Comp> {
+            type Vec = <$pt as SpatialPoint>::Vec;
+            type Scalar = <$pt as SpatialPoint>::Scalar;
+            type Comp = Comp;
+
+            fn k_nearest_neighbour(
+                &self,
+                loc: Self::Vec,
+                k: usize,
+            ) -> Vec<(Self::Scalar, Bounds<Self::Vec>)> {
+                let _span = info_span!("extent-k-nearest").entered();
+                let mut found = Vec::with_capacity(k.min(self.bounds.len()));
+                if k > 0 {
+                    self.nearest_node(0..self.bounds.len(), loc, k, 0, &mut found);
+                }
+                found
+            }
+
+            fn within_distance_with_distance(
+                &self,
+                loc: Self::Vec,
+                distance: Self::Scalar,
+            ) -> Vec<(Self::Scalar, Bounds<Self::Vec>)> {
+                let _span = info_span!("extent-within-distance").entered();
+                let max = distance * distance;
+                let mut found = Vec::new();
+                let _ = self.try_for_each_node(
+                    0..self.bounds.len(),
+                    &|min, node_max| loc.clamp(min, node_max).distance_squared(loc) <= max,
+                    &mut |bounds| {
+                        let d = bounds.distance_squared(loc);
+                        if d <= max {
+                            found.push((d, *bounds));
+                        }
+                        ControlFlow::Continue(())
+                    },
+                );
+                found
+            }
+
+            fn within_aabb(&self, min: Self::Vec, max: Self::Vec) -> Vec<Bounds<Self::Vec>> {
+                let _span = info_span!("extent-within-aabb").entered();
+                let (min, max) = (min.min(max), min.max(max));
+                let mut found = Vec::new();
+                let _ = self.try_for_each_node(
+                    0..self.bounds.len(),
+                    &|node_min, node_max| node_min.cmple(max).all() && min.cmple(node_max).all(),
+                    &mut |bounds| {
+                        if bounds.overlaps_aabb(min, max) {
+                            found.push(*bounds);
+                        }
+                        ControlFlow::Continue(())
+                    },
+                );
+                found
+            }
+
+            fn iter_bounds(&self) -> impl Iterator<Item = &Bounds<Self::Vec>> {
+                self.bounds.iter().filter(|bounds| self.is_live(bounds))
+            }
+        }
+
+        impl<Comp: TComp> UpdateExtentAccess for $treename<Comp> {
+            /// Rebuilds the tree, unless no bounds changed, were added or were removed since the last rebuild.
+            fn update(&mut self, data: impl Iterator<Item = (Bounds<Self::Vec>, bool)>) -> bool {
+                let mut changed = false;
+                self.buffer.clear();
+                self.buffer.extend(data.map(|(bounds, bounds_changed)| {
+                    changed |= bounds_changed;
+                    bounds
+                }));
+
+                // a different count means entities were added or removed
+                if !changed && self.buffer.len() == self.bounds.len() {
+                    return false;
+                }
+
+                std::mem::swap(&mut self.bounds, &mut self.buffer);
+                self.nodes.resize(
+                    self.bounds.len(),
+                    (
+                        <$pt as SpatialPoint>::Vec::ZERO,
+                        <$pt as SpatialPoint>::Vec::ZERO,
+                    ),
+                );
+                Self::build_node(&mut self.bounds, &mut self.nodes, 0);
+                self.removed.clear();
+                true
+            }
+
+            /// Marks the entity as removed, so queries skip it until the next update rebuilds the tree without it.
+            ///
+            /// Returns whether the entity was not marked as removed already.
+            fn remove_entity(&mut self, entity: Entity) -> bool {
+                self.removed.insert(entity)
+            }
+
+            fn clear(&mut self) {
+                self.bounds.clear();
+                self.nodes.clear();
+                self.removed.clear();
+            }
+        }
+    };
+}
+
+extent_impl!(crate::point::Point2, ExtentTree2);
+extent_impl!(crate::point::Point3, ExtentTree3);
+extent_impl!(crate::point::Point3A, ExtentTree3A);
+extent_impl!(crate::point::PointD2, ExtentTreeD2);
+extent_impl!(crate::point::PointD3, ExtentTreeD3);
@@ -0,0 +1,604 @@
+//! A uniform grid (spatial hash) as a spatial datastructure in ``bevy_spatial``.
+//!
+//! Every point is hashed into a cell of a fixed size. Queries only look at the cells which could contain results.
+//! Like the [`rtree`](crate::rtree) trees, grids are updated incrementally, but updates are even cheaper.
+//! This works best for dense, evenly distributed points where the cell size is close to the typical query distance.
+
+use bevy::{ecs::entity::EntityHashMap, prelude::*, utils::HashMap};
+use smallvec::{smallvec, SmallVec};
+
+use crate::{
+    point::{in_aabb, ray_hit, squared, SpatialPoint},
+    spatial_access::{
+        insert_nearest, sort_by_scalar, NearestFound, NearestInto, SpatialAABBAccess,
+        SpatialAccess, SpatialPairAccess, SpatialRayAccess, UpdateSpatialAccess,
+    },
+    TComp,
+};
+
+use num_traits::Bounded;
+use std::{marker::PhantomData, ops::ControlFlow};
+
+/// Calls `f` for every cell between `min` and `max` (inclusive), until it breaks.
+fn for_each_cell<const N: usize>(
+    min: [i64; N],
+    max: [i64; N],
+    mut f: impl FnMut([i64; N]) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    let mut cell = min;
+    loop {
+        f(cell)?;
+        let mut axis = 0;
+        loop {
+            if axis == N {
+                return ControlFlow::Continue(());
+            }
+            if cell[axis] < max[axis] {
+                cell[axis] += 1;
+                break;
+            }
+            cell[axis] = min[axis];
+            axis += 1;
+        }
+    }
+}
+
+/// Number of cells between `min` and `max` (inclusive), saturating.
+fn cell_count<const N: usize>(min: [i64; N], max: [i64; N]) -> u64 {
+    min.iter().zip(max).fold(1u64, |count, (lo, hi)| {
+        count.saturating_mul(hi.abs_diff(*lo).saturating_add(1))
+    })
+}
+
+/// Whether `cell` lies between `min` and `max` (inclusive).
+fn contains_cell<const N: usize>(min: [i64; N], max: [i64; N], cell: [i64; N]) -> bool {
+    (0..N).all(|i| min[i] <= cell[i] && cell[i] <= max[i])
+}
+
+/// The chebyshev distance between two cells, in cells.
+fn ring_of<const N: usize>(center: [i64; N], cell: [i64; N]) -> u64 {
+    (0..N)
+        .map(|i| center[i].abs_diff(cell[i]))
+        .max()
+        .unwrap_or(0)
+}
+
+macro_rules! grid_impl {
+    ($pt:ty, $gridname:ident, $dim:literal) => {
+        /// Resource for storing a uniform grid of points.
+        ///
+        /// Keeps track of which points belong to which [`Entity`], so moved and removed entities can be updated in place.
+        #[derive(Resource)]
+        pub struct $gridname<Comp> {
+            cell_size: <$pt as SpatialPoint>::Scalar,
+            cells: HashMap<[i64; $dim], Vec<$pt>>,
+            entities: EntityHashMap<SmallVec<[$pt; 1]>>,
+            len: usize,
+            component_type: PhantomData<Comp>,
+        }
+
+        impl<Comp> $gridname<Comp> {
+            /// Create a new, empty grid with cells of the given size.
+            ///
+            /// The cell size should roughly match the distances used in queries.
+            ///
+            /// # Panics
+            /// Panics if `cell_size` is not positive.
+            #[must_use]
+            pub fn new(cell_size: <$pt as SpatialPoint>::Scalar) -> Self {
+                assert!(cell_size > 0.0, "grid cell size must be positive");
+                Self {
+                    cell_size,
+                    cells: default(),
+                    entities: default(),
+                    len: 0,
+                    component_type: PhantomData,
+                }
+            }
+
+            /// Get the size of a single cell.
+            #[must_use]
+            pub fn cell_size(&self) -> <$pt as SpatialPoint>::Scalar {
+                self.cell_size
+            }
+
+            /// Change the size of a single cell, rehashing all points.
+            ///
+            /// # Panics
+            /// Panics if `cell_size` is not positive.
+            pub fn set_cell_size(&mut self, cell_size: <$pt as SpatialPoint>::Scalar) {
+                assert!(cell_size > 0.0, "grid cell size must be positive");
+                self.cell_size = cell_size;
+                let points: Vec<$pt> = self.cells.drain().flat_map(|(_, points)| points).collect();
+                for point in points {
+                    self.cells
+                        .entry(self.cell_of(point.vec))
+                        .or_default()
+                        .push(point);
+                }
+            }
+
+            /// The number of points stored in the grid.
+            #[must_use]
+            pub fn len(&self) -> usize {
+                self.len
+            }
+
+            /// Whether the grid contains no points.
+            #[must_use]
+            pub fn is_empty(&self) -> bool {
+                self.len == 0
+            }
+
+            #[allow(clippy::cast_possible_truncation)]
+            fn cell_of(&self, vec: <$pt as SpatialPoint>::Vec) -> [i64; $dim] {
+                std::array::from_fn(|i| (vec[i] / self.cell_size).floor() as i64)
+            }
+
+            /// Calls `f` for every point in the cells between `min` and `max`, until it breaks.
+            ///
+            /// Falls back to looking at every occupied cell if that is cheaper.
+            fn for_each_in_cells(
+                &self,
+                min: [i64; $dim],
+                max: [i64; $dim],
+                mut f: impl FnMut(&$pt) -> ControlFlow<()>,
+            ) -> ControlFlow<()> {
+                if cell_count(min, max) > self.cells.len() as u64 {
+                    self.cells
+                        .iter()
+                        .filter(|(cell, _)| contains_cell(min, max, **cell))
+                        .flat_map(|(_, points)| points)
+                        .try_for_each(f)
+                } else {
+                    for_each_cell(min, max, |cell| match self.cells.get(&cell) {
+                        Some(points) => points.iter().try_for_each(&mut f),
+                        None => ControlFlow::Continue(()),
+                    })
+                }
+            }
+
+            /// The `k` nearest points to `loc` which pass `filter`, together with their squared distance, sorted by distance.
+            fn nearest_points(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                max_distance_squared: <$pt as SpatialPoint>::Scalar,
+                filter: impl FnMut(&$pt) -> bool,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, &$pt)> {
+                let mut found = Vec::with_capacity(k);
+                self.search_nearest(loc, k, max_distance_squared, filter, &mut found);
+                found
+            }
+
+            /// Search the cells in rings around `loc`, until the `k` nearest points passing `filter` are found.
+            ///
+            /// Points further away than `max_distance_squared` are skipped, rings further away are not searched.
+            /// Collects the points into `found`, sorted by distance.
+            #[allow(clippy::cast_precision_loss)]
+            fn search_nearest<'a>(
+                &'a self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                max_distance_squared: <$pt as SpatialPoint>::Scalar,
+                mut filter: impl FnMut(&$pt) -> bool,
+                found: &mut impl NearestFound<<$pt as SpatialPoint>::Scalar, &'a $pt>,
+            ) {
+                let p: $pt = loc.into();
+                let center = self.cell_of(loc);
+                let mut visited = 0;
+
+                if k == 0 {
+                    return;
+                }
+
+                for ring in 0u64.. {
+                    let min = center.map(|c| c.saturating_sub_unsigned(ring));
+                    let max = center.map(|c| c.saturating_add_unsigned(ring));
+
+                    if cell_count(min, max) > self.cells.len() as u64 {
+                        // fewer cells are occupied than this ring contains, so look at the remaining occupied cells directly.
+                        self.cells
+                            .iter()
+                            .filter(|(cell, _)| ring_of(center, **cell) >= ring)
+                            .flat_map(|(_, points)| points)
+                            .map(|point| (point.distance_squared(&p), point))
+                            .filter(|(distance, point)| {
+                                *distance <= max_distance_squared && filter(point)
+                            })
+                            .for_each(|(distance, point)| {
+                                insert_nearest(found, k, distance, point);
+                            });
+                        break;
+                    }
+
+                    let _ = for_each_cell(min, max, |cell| {
+                        if ring_of(center, cell) == ring {
+                            if let Some(points) = self.cells.get(&cell) {
+                                visited += points.len();
+                                for point in points {
+                                    let distance = point.distance_squared(&p);
+                                    if distance <= max_distance_squared && filter(point) {
+                                        insert_nearest(found, k, distance, point);
+                                    }
+                                }
+                            }
+                        }
+                        ControlFlow::Continue(())
+                    });
+
+                    // every point outside of the rings searched so far is at least this far away.
+                    let searched = ring as <$pt as SpatialPoint>::Scalar * self.cell_size;
+                    if visited == self.len
+                        || squared(searched) > max_distance_squared
+                        || (found.count() == k && found.distance_at(k - 1) <= squared(searched))
+                    {
+                        break;
+                    }
+                }
+            }
+
+            fn insert_point(&mut self, point: $pt) {
+                self.cells
+                    .entry(self.cell_of(point.vec))
+                    .or_default()
+                    .push(point);
+                self.len += 1;
+            }
+
+            /// Adds another point of an entity, keeping its previous points.
+            fn add_sample(&mut self, point: $pt) {
+                if let Some(entity) = point.entity {
+                    self.entities.entry(entity).or_default().push(point);
+                }
+                self.insert_point(point);
+            }
+
+            fn remove_from_cell(&mut self, point: &$pt) -> bool {
+                let cell = self.cell_of(point.vec);
+                let Some(points) = self.cells.get_mut(&cell) else {
+                    return false;
+                };
+                let Some(idx) = points.iter().position(|p| p == point) else {
+                    return false;
+                };
+                points.swap_remove(idx);
+                if points.is_empty() {
+                    self.cells.remove(&cell);
+                }
+                self.len -= 1;
+                true
+            }
+        }
+
+        impl<Comp> SpatialAccess for $gridname<Comp>
+        where
+            Comp: TComp,
+        {
+            type Point = $pt;
+
+            type Comp = Comp;
+            type ResultT = (<$pt as SpatialPoint>::Vec, Option<Entity>);
+
+            /// Get the nearest neighbour to a position.
+            fn nearest_neighbour(&self, loc: <$pt as SpatialPoint>::Vec) -> Option<Self::ResultT> {
+                self.nearest_points(loc, 1, Bounded::max_value(), |_| true)
+                    .first()
+                    .map(|(_, point)| (point.vec(), point.entity()))
+            }
+
+            /// Get the `k` neighbours to `loc`
+            ///
+            /// If `loc` is the location of a tracked entity, you might want to skip the first.
+            fn k_nearest_neighbour(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+            ) -> Vec<Self::ResultT> {
+                let _span = info_span!("k-nearest").entered();
+
+                self.nearest_points(loc, k, Bounded::max_value(), |_| true)
+                    .iter()
+                    .map(|(_, point)| (point.vec(), point.entity()))
+                    .collect()
+            }
+
+            /// Get the `k` neighbours to `loc` whose entities pass `filter`
+            fn k_nearest_neighbour_filtered(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                mut filter: impl FnMut(Entity) -> bool,
+            ) -> Vec<Self::ResultT> {
+                let _span = info_span!("k-nearest-filtered").entered();
+
+                self.nearest_points(loc, k, Bounded::max_value(), |point| {
+                    point.entity.is_some_and(&mut filter)
+                })
+                .iter()
+                .map(|(_, point)| (point.vec(), point.entity()))
+                .collect()
+            }
+
+            /// Get up to `k` neighbours to `loc` within `max_distance`
+            ///
+            /// Rings of cells further away than `max_distance` are never searched.
+            fn k_nearest_within(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                max_distance: <$pt as SpatialPoint>::Scalar,
+            ) -> Vec<Self::ResultT> {
+                let _span = info_span!("k-nearest-within").entered();
+
+                self.nearest_points(loc, k, squared(max_distance), |_| true)
+                    .iter()
+                    .map(|(_, point)| (point.vec(), point.entity()))
+                    .collect()
+            }
+
+            /// Get all entities within a certain distance (radius) of `loc`
+            fn within_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                distance: <$pt as SpatialPoint>::Scalar,
+            ) -> Vec<Self::ResultT> {
+                let mut result = vec![];
+                self.within_distance_into(loc, distance, &mut result);
+                result
+            }
+
+            /// Call `f` for every entity within a certain distance (radius) of `loc`
+            fn for_each_within_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                distance: <$pt as SpatialPoint>::Scalar,
+                mut f: impl FnMut(Self::ResultT) -> ControlFlow<()>,
+            ) {
+                let _span = info_span!("within-distance").entered();
+                let p: $pt = loc.into();
+                let distance_squared = squared(distance);
+
+                let _ = self.for_each_in_cells(
+                    self.cell_of(loc - distance),
+                    self.cell_of(loc + distance),
+                    |point| {
+                        if point.distance_squared(&p) <= distance_squared {
+                            f((point.vec(), point.entity()))
+                        } else {
+                            ControlFlow::Continue(())
+                        }
+                    },
+                );
+            }
+
+            /// Append the `k` neighbours to `loc` to `out`
+            fn k_nearest_neighbour_into(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                out: &mut Vec<Self::ResultT>,
+            ) {
+                let _span = info_span!("k-nearest").entered();
+
+                let p: $pt = loc.into();
+                let mut found = NearestInto::new(out, |vec| <$pt>::from(vec).distance_squared(&p));
+                self.search_nearest(loc, k, Bounded::max_value(), |_| true, &mut found);
+            }
+
+            /// Get the nearest neighbour to a position, together with its squared distance.
+            fn nearest_neighbour_with_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+            ) -> Option<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                self.nearest_points(loc, 1, Bounded::max_value(), |_| true)
+                    .first()
+                    .map(|(distance, point)| (*distance, (point.vec(), point.entity())))
+            }
+
+            /// Get the `k` neighbours to `loc`, together with their squared distance.
+            fn k_nearest_neighbour_with_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let _span = info_span!("k-nearest").entered();
+
+                self.nearest_points(loc, k, Bounded::max_value(), |_| true)
+                    .iter()
+                    .map(|(distance, point)| (*distance, (point.vec(), point.entity())))
+                    .collect()
+            }
+
+            /// Get the `k` neighbours to `loc` whose entities pass `filter`, together with their squared distance.
+            fn k_nearest_neighbour_filtered_with_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                mut filter: impl FnMut(Entity) -> bool,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let _span = info_span!("k-nearest-filtered").entered();
+
+                self.nearest_points(loc, k, Bounded::max_value(), |point| {
+                    point.entity.is_some_and(&mut filter)
+                })
+                .iter()
+                .map(|(distance, point)| (*distance, (point.vec(), point.entity())))
+                .collect()
+            }
+        }
+
+        impl<Comp> SpatialPairAccess for $gridname<Comp>
+        where
+            Comp: TComp,
+        {
+            fn iter_points(&self) -> impl Iterator<Item = &$pt> {
+                self.cells.values().flatten()
+            }
+
+            /// Call `f` for every pair of points within `distance` of each other
+            ///
+            /// Only compares points in the cells around each point.
+            fn for_each_pair_within_distance(
+                &self,
+                distance: <$pt as SpatialPoint>::Scalar,
+                mut f: impl FnMut(&$pt, &$pt),
+            ) {
+                let _span = info_span!("pairs-within-distance").entered();
+                let distance_squared = squared(distance);
+
+                for a in self.cells.values().flatten() {
+                    let _ = self.for_each_in_cells(
+                        self.cell_of(a.vec - distance),
+                        self.cell_of(a.vec + distance),
+                        |b| {
+                            if std::ptr::from_ref(a) < std::ptr::from_ref(b)
+                                && a.distance_squared(b) <= distance_squared
+                            {
+                                f(a, b);
+                            }
+                            ControlFlow::Continue(())
+                        },
+                    );
+                }
+            }
+        }
+
+        impl<Comp> SpatialRayAccess for $gridname<Comp>
+        where
+            Comp: TComp,
+        {
+            /// Get all entities within `radius` of a ray, sorted by their position along the ray
+            fn cast_ray(
+                &self,
+                origin: <$pt as SpatialPoint>::Vec,
+                direction: <$pt as SpatialPoint>::Vec,
+                max_t: <$pt as SpatialPoint>::Scalar,
+                radius: <$pt as SpatialPoint>::Scalar,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let _span = info_span!("cast-ray").entered();
+                let (o, d): ($pt, $pt) = (origin.into(), direction.into());
+                let end = origin + direction * max_t;
+
+                let mut hits = vec![];
+                let _ = self.for_each_in_cells(
+                    self.cell_of(origin.min(end) - radius),
+                    self.cell_of(origin.max(end) + radius),
+                    |point| {
+                        if let Some(t) = ray_hit(point, &o, &d, max_t, radius) {
+                            hits.push((t, (point.vec(), point.entity())));
+                        }
+                        ControlFlow::Continue(())
+                    },
+                );
+                sort_by_scalar(&mut hits);
+                hits
+            }
+        }
+
+        impl<Comp> SpatialAABBAccess for $gridname<Comp>
+        where
+            Comp: TComp,
+        {
+            /// Get all entities inside the axis-aligned bounding box spanned by `min` and `max`
+            fn within_aabb(
+                &self,
+                min: <$pt as SpatialPoint>::Vec,
+                max: <$pt as SpatialPoint>::Vec,
+            ) -> Vec<Self::ResultT> {
+                let _span = info_span!("within-aabb").entered();
+                let (a, b): ($pt, $pt) = (min.into(), max.into());
+                let (min, max): ($pt, $pt) = (a.min_point(&b).into(), a.max_point(&b).into());
+
+                let mut result = vec![];
+                let _ =
+                    self.for_each_in_cells(self.cell_of(min.vec), self.cell_of(max.vec), |point| {
+                        if in_aabb(point, &min, &max) {
+                            result.push((point.vec(), point.entity()));
+                        }
+                        ControlFlow::Continue(())
+                    });
+                result
+            }
+        }
+
+        impl<Comp: TComp> UpdateSpatialAccess for $gridname<Comp> {
+            /// Only changed points are moved and only removed entities are removed.
+            ///
+            /// Several points of the same entity have to be next to each other, they replace its previous points together.
+            fn update(
+                &mut self,
+                data: impl Iterator<Item = (Self::Point, bool)>,
+                removed: impl Iterator<Item = Entity>,
+            ) -> bool {
+                let mut updated = false;
+                for entity in removed {
+                    updated |= self.remove_entity(entity);
+                }
+                // consecutive points of the same entity are its samples, which replace its previous points together
+                let mut last = None;
+                for (p, changed) in data {
+                    if changed {
+                        if p.entity.is_some() && p.entity == last {
+                            self.add_sample(p);
+                        } else {
+                            self.add(p);
+                        }
+                        last = p.entity;
+                        updated = true;
+                    }
+                }
+                updated
+            }
+
+            /// Adds the point, replacing the previous points of the same [`Entity`] if there were any.
+            fn add(&mut self, point: Self::Point) {
+                if let Some(old) = point
+                    .entity
+                    .and_then(|e| self.entities.insert(e, smallvec![point]))
+                {
+                    if old.as_slice() == [point] {
+                        return;
+                    }
+                    for old in &old {
+                        self.remove_from_cell(old);
+                    }
+                }
+                self.insert_point(point);
+            }
+
+            fn remove_point(&mut self, point: Self::Point) -> bool {
+                if let Some(entity) = point.entity {
+                    if let Some(points) = self.entities.get_mut(&entity) {
+                        if let Some(idx) = points.iter().position(|p| *p == point) {
+                            points.swap_remove(idx);
+                        }
+                        if points.is_empty() {
+                            self.entities.remove(&entity);
+                        }
+                    }
+                }
+                self.remove_from_cell(&point)
+            }
+
+            fn remove_entity(&mut self, entity: Entity) -> bool {
+                self.entities.remove(&entity).is_some_and(|points| {
+                    points.iter().fold(false, |removed, point| {
+                        self.remove_from_cell(point) | removed
+                    })
+                })
+            }
+
+            fn clear(&mut self) {
+                self.cells.clear();
+                self.entities.clear();
+                self.len = 0;
+            }
+        }
+    };
+}
+grid_impl!(crate::point::Point2, Grid2, 2);
+grid_impl!(crate::point::Point3, Grid3, 3);
+grid_impl!(crate::point::Point3A, Grid3A, 3);
+grid_impl!(crate::point::PointD2, GridD2, 2);
+grid_impl!(crate::point::PointD3, GridD3, 3);
@@ -1,21 +1,367 @@
 //! implementations to use [`kd_tree`] trees as a spatial datastructure in ``bevy_spatial``.
 
-use bevy::prelude::*;
+use bevy::{
+    ecs::entity::EntityHashMap,
+    math::DVec3,
+    prelude::*,
+    tasks::{block_on, poll_once, AsyncComputeTaskPool, Task, TaskPool},
+};
 use kd_tree::{KdPoint, KdTree as BaseKdTree, KdTreeN};
 
 use crate::{
-    point::SpatialPoint,
-    spatial_access::{SpatialAccess, UpdateSpatialAccess},
+    metric::{Euclidean, Metric},
+    point::{
+        add_saturating, clip_ray, in_aabb, ray_hit, squared, Scalar, SpatialPoint,
+        VecFromCoordinate,
+    },
+    spatial_access::{
+        batch, insert_nearest, sort_by_scalar, NearestFound, NearestInto, SpatialAABBAccess,
+        SpatialAccess, SpatialMetricAccess, SpatialPairAccess, SpatialRayAccess,
+        UpdateSpatialAccess,
+    },
     TComp,
 };
 
-use std::marker::PhantomData;
+use num_traits::{Bounded, Float, One, Zero};
+use smallvec::{smallvec, SmallVec};
+use std::{marker::PhantomData, ops::ControlFlow};
+use typenum::Unsigned;
 
 use bevy::prelude::Resource;
 
 #[cfg(all(feature = "kdtree_rayon", target_arch = "wasm32"))]
 compile_error!("bevy-spatial feature \"kdtree_rayon\" is incompatible with target_arch = \"wasm32\" builds. Disable default-features and enable kdtree");
 
+/// Calls `f` for every point of the kd-sorted `items` inside the AABB from `min` to `max`.
+///
+/// `items` are sorted like [`kd_tree`] does: the median along `axis` is in the middle,
+/// with the smaller half before and the larger half after it, recursively along the next axis.
+fn for_each_in_aabb<P: SpatialPoint>(
+    items: &[P],
+    min: &P,
+    max: &P,
+    axis: usize,
+    f: &mut impl FnMut(&P),
+) {
+    if items.is_empty() {
+        return;
+    }
+    let mid = items.len() / 2;
+    let item = &items[mid];
+    if in_aabb(item, min, max) {
+        f(item);
+    }
+
+    let next_axis = (axis + 1) % P::Dimension::USIZE;
+    if min.at(axis) <= item.at(axis) {
+        for_each_in_aabb(&items[..mid], min, max, next_axis, f);
+    }
+    if item.at(axis) <= max.at(axis) {
+        for_each_in_aabb(&items[mid + 1..], min, max, next_axis, f);
+    }
+}
+
+/// A k-nearest search as used by [`nearest_filtered`].
+struct KdNearest<'m, P: SpatialPoint, M> {
+    loc: P,
+    k: usize,
+    metric: &'m M,
+    /// Points further away than this distance, as measured by `metric`, are skipped.
+    max_distance: P::Scalar,
+}
+
+/// Collects the `k` nearest points of the kd-sorted `items` to `search.loc` which pass `filter` into `found`, sorted by distance.
+///
+/// The side of each split containing `loc` is searched first, the other side only if it can still contain
+/// a point closer than the `k`th found one and within the maximum distance.
+fn nearest_filtered<'a, P: SpatialPoint, M: Metric<P>>(
+    items: &'a [P],
+    search: &KdNearest<P, M>,
+    axis: usize,
+    filter: &mut impl FnMut(&P) -> bool,
+    found: &mut impl NearestFound<P::Scalar, &'a P>,
+) {
+    if items.is_empty() {
+        return;
+    }
+    let mid = items.len() / 2;
+    let item = &items[mid];
+    let distance = search.metric.distance(item, &search.loc);
+    if distance <= search.max_distance && filter(item) {
+        insert_nearest(found, search.k, distance, item);
+    }
+
+    let next_axis = (axis + 1) % P::Dimension::USIZE;
+    let diff = search.loc.at(axis) - item.at(axis);
+    let (near, far) = if diff <= P::Scalar::zero() {
+        (&items[..mid], &items[mid + 1..])
+    } else {
+        (&items[mid + 1..], &items[..mid])
+    };
+    nearest_filtered(near, search, next_axis, filter, found);
+    let bound = if found.count() < search.k {
+        search.max_distance
+    } else {
+        found.distance_at(search.k - 1)
+    };
+    if search.metric.axis_distance(axis, diff) <= bound {
+        nearest_filtered(far, search, next_axis, filter, found);
+    }
+}
+
+/// Calls `f` for every point of the kd-sorted `items` within `distance` of `loc` as measured by `metric`, until `f` breaks.
+fn try_for_each_within<P: SpatialPoint>(
+    items: &[P],
+    loc: &P,
+    metric: &impl Metric<P>,
+    distance: P::Scalar,
+    axis: usize,
+    f: &mut impl FnMut(&P) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    if items.is_empty() {
+        return ControlFlow::Continue(());
+    }
+    let mid = items.len() / 2;
+    let item = &items[mid];
+    if metric.distance(item, loc) <= distance {
+        f(item)?;
+    }
+
+    let next_axis = (axis + 1) % P::Dimension::USIZE;
+    let diff = loc.at(axis) - item.at(axis);
+    let reachable = metric.axis_distance(axis, diff) <= distance;
+    if diff <= P::Scalar::zero() || reachable {
+        try_for_each_within(&items[..mid], loc, metric, distance, next_axis, f)?;
+    }
+    if diff >= P::Scalar::zero() || reachable {
+        try_for_each_within(&items[mid + 1..], loc, metric, distance, next_axis, f)?;
+    }
+    ControlFlow::Continue(())
+}
+
+/// The region a kd subtree covers, narrowed down by the splits above it.
+///
+/// Only the first [`SpatialPoint::Dimension`] axes are used, all point types have at most 3.
+#[derive(Clone, Copy)]
+struct KdBounds<S> {
+    min: [S; 3],
+    max: [S; 3],
+}
+
+impl<S: Scalar> KdBounds<S> {
+    fn unbounded() -> Self {
+        Self {
+            min: [S::min_value(); 3],
+            max: [S::max_value(); 3],
+        }
+    }
+
+    /// The bounds of the subtrees below and above a split at `at` along `axis`.
+    fn split(self, axis: usize, at: S) -> (Self, Self) {
+        let (mut below, mut above) = (self, self);
+        below.max[axis] = at;
+        above.min[axis] = at;
+        (below, above)
+    }
+
+    /// The smallest possible squared distance between a point inside of these bounds and one inside of `other`.
+    fn distance_squared(&self, other: &Self, dim: usize) -> S {
+        let mut distance = S::zero();
+        for axis in 0..dim {
+            let gap = if self.min[axis] > other.max[axis] {
+                self.min[axis] - other.max[axis]
+            } else if other.min[axis] > self.max[axis] {
+                other.min[axis] - self.max[axis]
+            } else {
+                S::zero()
+            };
+            distance = add_saturating(distance, squared(gap));
+        }
+        distance
+    }
+}
+
+/// One side of a [`dual_join`]: a kd-sorted slice, the region it covers and the axis it is split along.
+#[derive(Clone, Copy)]
+struct KdNode<'a, P: SpatialPoint> {
+    items: &'a [P],
+    bounds: KdBounds<P::Scalar>,
+    axis: usize,
+}
+
+impl<'a, P: SpatialPoint> KdNode<'a, P> {
+    fn root(items: &'a [P]) -> Self {
+        Self {
+            items,
+            bounds: KdBounds::unbounded(),
+            axis: 0,
+        }
+    }
+
+    /// The median point and the subtrees below and above it.
+    fn split(self) -> (&'a P, Self, Self) {
+        let mid = self.items.len() / 2;
+        let item = &self.items[mid];
+        let (below, above) = self.bounds.split(self.axis, item.at(self.axis));
+        let axis = (self.axis + 1) % P::Dimension::USIZE;
+        (
+            item,
+            Self {
+                items: &self.items[..mid],
+                bounds: below,
+                axis,
+            },
+            Self {
+                items: &self.items[mid + 1..],
+                bounds: above,
+                axis,
+            },
+        )
+    }
+}
+
+/// Calls `f` for every pair of a point in `a` and a point in `b` within `distance_squared` of each other.
+///
+/// Descends both trees at once, always splitting the larger node,
+/// and skips pairs of subtrees whose regions are too far apart.
+fn dual_join<P: SpatialPoint>(
+    a: KdNode<P>,
+    b: KdNode<P>,
+    distance_squared: P::Scalar,
+    f: &mut impl FnMut(&P, &P),
+) {
+    if a.items.is_empty()
+        || b.items.is_empty()
+        || a.bounds.distance_squared(&b.bounds, P::Dimension::USIZE) > distance_squared
+    {
+        return;
+    }
+
+    if a.items.len() >= b.items.len() {
+        let (item, below, above) = a.split();
+        let _ = try_for_each_within(
+            b.items,
+            item,
+            &Euclidean,
+            distance_squared,
+            b.axis,
+            &mut |q| {
+                f(item, q);
+                ControlFlow::Continue(())
+            },
+        );
+        dual_join(below, b, distance_squared, f);
+        dual_join(above, b, distance_squared, f);
+    } else {
+        let (item, below, above) = b.split();
+        let _ = try_for_each_within(
+            a.items,
+            item,
+            &Euclidean,
+            distance_squared,
+            a.axis,
+            &mut |p| {
+                f(p, item);
+                ControlFlow::Continue(())
+            },
+        );
+        dual_join(a, below, distance_squared, f);
+        dual_join(a, above, distance_squared, f);
+    }
+}
+
+/// A ray as used by [`for_each_on_ray`], see [`SpatialRayAccess::cast_ray`].
+struct KdRay<P: SpatialPoint> {
+    origin: P,
+    direction: P,
+    max_t: P::Scalar,
+    radius: P::Scalar,
+}
+
+/// Calls `f` for every point of the kd-sorted `items` within the radius of `ray`, together with its `t`.
+///
+/// `range` is the part of the ray which can still reach the points in `items`.
+/// It is narrowed down on every split, so subtrees which the swept volume of the ray misses are skipped.
+fn for_each_on_ray<P: SpatialPoint>(
+    items: &[P],
+    ray: &KdRay<P>,
+    range: (P::Scalar, P::Scalar),
+    axis: usize,
+    f: &mut impl FnMut(P::Scalar, &P),
+) where
+    P::Scalar: Float,
+{
+    if items.is_empty() {
+        return;
+    }
+    let mid = items.len() / 2;
+    let item = &items[mid];
+    if let Some(t) = ray_hit(item, &ray.origin, &ray.direction, ray.max_t, ray.radius) {
+        f(t, item);
+    }
+
+    let next_axis = (axis + 1) % P::Dimension::USIZE;
+    let (origin, direction, split) = (ray.origin.at(axis), ray.direction.at(axis), item.at(axis));
+    if let Some(range) = clip_ray(origin, direction, split + ray.radius, true, range) {
+        for_each_on_ray(&items[..mid], ray, range, next_axis, f);
+    }
+    if let Some(range) = clip_ray(origin, direction, split - ray.radius, false, range) {
+        for_each_on_ray(&items[mid + 1..], ray, range, next_axis, f);
+    }
+}
+
+/// Trees which can be rebuilt in the background, used by [`AutomaticUpdate`](crate::AutomaticUpdate) to poll them.
+pub(crate) trait AsyncRebuild: Resource {
+    /// Build new trees in the background from now on.
+    fn enable_async_rebuild(&mut self);
+    /// Swaps in the tree built in the background, if it is finished. Returns whether it was swapped in.
+    fn poll_rebuild(&mut self) -> bool;
+}
+
+/// Trees which can wrap around at the edges of the world, used by [`AutomaticUpdate`](crate::AutomaticUpdate) to configure them.
+pub(crate) trait WrapAround: Resource {
+    /// Wrap around at `extents`, see the `with_wrap` method of the trees.
+    fn set_wrap(&mut self, extents: DVec3);
+}
+
+/// Scalars trees can be built for, floats are sorted as ordered floats and integers directly.
+trait KdScalar: Scalar {
+    /// Build a tree from `points`, in parallel with the `kdtree_rayon` feature.
+    fn build<P: KdPoint<Scalar = Self> + Send>(points: Vec<P>) -> BaseKdTree<P>;
+    /// Build a tree from `points` on the current thread, for builds running inside of a task.
+    fn build_sequential<P: KdPoint<Scalar = Self>>(points: Vec<P>) -> BaseKdTree<P>;
+}
+
+macro_rules! kd_scalar_impl {
+    ($scalar:ty, $build:ident, $par_build:ident) => {
+        impl KdScalar for $scalar {
+            #[cfg(feature = "kdtree_rayon")]
+            fn build<P: KdPoint<Scalar = Self> + Send>(points: Vec<P>) -> BaseKdTree<P> {
+                KdTreeN::$par_build(points)
+            }
+
+            #[cfg(any(not(feature = "kdtree_rayon"), target_arch = "wasm32"))]
+            fn build<P: KdPoint<Scalar = Self> + Send>(points: Vec<P>) -> BaseKdTree<P> {
+                Self::build_sequential(points)
+            }
+
+            fn build_sequential<P: KdPoint<Scalar = Self>>(points: Vec<P>) -> BaseKdTree<P> {
+                KdTreeN::$build(points)
+            }
+        }
+    };
+}
+
+kd_scalar_impl!(f32, build_by_ordered_float, par_build_by_ordered_float);
+kd_scalar_impl!(f64, build_by_ordered_float, par_build_by_ordered_float);
+kd_scalar_impl!(i32, build, par_build);
+kd_scalar_impl!(i64, build, par_build);
+
+/// Whether positions wrap around along an axis with this extent, which is the case for positive and finite extents.
+fn wraps<S: Scalar>(extent: S) -> bool {
+    extent > S::zero() && extent < S::max_value()
+}
+
 macro_rules! kdtree_impl {
     ($pt:ty, $treename:ident) => {
         impl KdPoint for $pt {
@@ -29,22 +375,422 @@ macro_rules! kdtree_impl {
         }
 
         /// Resource for storing a ``KdTree``
+        ///
+        /// The tree is only rebuilt when a point changed, was added or was removed.
+        /// Entities removed in between are skipped by queries, see [`is_removed`](Self::is_removed).
         #[derive(Resource)]
         pub struct $treename<Comp> {
             /// The ``KdTree``
             pub tree: BaseKdTree<$pt>,
+            dirty: bool,
+            rebuilt: bool,
+            /// The points of the last update, kept to avoid allocating on updates which don't rebuild the tree.
+            points: Vec<$pt>,
+            async_rebuild: bool,
+            task: Option<(u64, Task<BaseKdTree<$pt>>)>,
+            /// Entities removed since the last rebuild, with the generation they were removed in.
+            tombstones: EntityHashMap<u64>,
+            generation: u64,
+            /// The size of the world along each axis, if positions wrap around at its edges.
+            wrap: Option<<$pt as SpatialPoint>::Vec>,
             component_type: PhantomData<Comp>,
         }
 
+        impl<Comp> $treename<Comp> {
+            /// Whether the last call to [`UpdateSpatialAccess::update`] rebuilt the tree.
+            ///
+            /// `false` if nothing changed since the previous update, so the tree was kept as is.
+            /// With [`with_async_rebuild`](Self::with_async_rebuild), whether the last call to
+            /// [`poll_rebuild`](Self::poll_rebuild) swapped in a new tree instead.
+            #[must_use]
+            pub fn rebuilt_last_update(&self) -> bool {
+                self.rebuilt
+            }
+
+            /// Build new trees in the background on the [`AsyncComputeTaskPool`] instead of blocking in [`UpdateSpatialAccess::update`].
+            ///
+            /// Each tree is built on a single thread of the pool, which is created if no plugin set it up.
+            /// Queries keep using the previous tree until [`poll_rebuild`](Self::poll_rebuild) swaps in the new one.
+            #[must_use]
+            pub fn with_async_rebuild(self, async_rebuild: bool) -> Self {
+                Self {
+                    async_rebuild,
+                    ..self
+                }
+            }
+
+            /// Make positions wrap around at the edges of a world from zero to `extents`, like on a torus.
+            ///
+            /// Points are moved into the world on each update, and [`nearest_neighbour`](SpatialAccess::nearest_neighbour),
+            /// [`k_nearest_neighbour`](SpatialAccess::k_nearest_neighbour), [`within_distance`](SpatialAccess::within_distance)
+            /// and their variants find points across the edges, returning their position inside of the world.
+            /// Axes with an extent of zero or infinity don't wrap.
+            ///
+            /// The AABB, pair, join and ray queries find points across the edges too,
+            /// AABBs and rays are given in world positions and can stick out of the world.
+            /// A ray going around the world hits a point once for every time it passes it.
+            #[must_use]
+            pub fn with_wrap(self, extents: <$pt as SpatialPoint>::Vec) -> Self {
+                Self {
+                    wrap: Some(extents),
+                    ..self
+                }
+            }
+
+            /// The size of the world along each axis, if positions wrap around at its edges.
+            #[must_use]
+            pub fn wrap(&self) -> Option<<$pt as SpatialPoint>::Vec> {
+                self.wrap
+            }
+
+            /// Moves `vec` into the world from zero to the extents set by [`with_wrap`](Self::with_wrap), along the axes which wrap.
+            #[must_use]
+            pub fn normalize(
+                &self,
+                mut vec: <$pt as SpatialPoint>::Vec,
+            ) -> <$pt as SpatialPoint>::Vec {
+                if let Some(extents) = self.wrap {
+                    for i in 0..<$pt as SpatialPoint>::Dimension::USIZE {
+                        if wraps(extents[i]) {
+                            vec[i] = vec[i].rem_euclid(extents[i]);
+                        }
+                    }
+                }
+                vec
+            }
+
+            /// The locations to search around to find all points within `distance` of `loc`, as measured by `metric`, across the wrapped edges.
+            ///
+            /// Each point is closest to exactly one of them, see [`is_nearest_image`](Self::is_nearest_image).
+            fn images(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                metric: &impl Metric<$pt>,
+                distance: <$pt as SpatialPoint>::Scalar,
+            ) -> SmallVec<[<$pt as SpatialPoint>::Vec; 8]> {
+                let Some(extents) = self.wrap else {
+                    return smallvec![loc];
+                };
+                let loc = self.normalize(loc);
+                let one: <$pt as SpatialPoint>::Scalar = One::one();
+                let two = one + one;
+
+                let mut images: SmallVec<[_; 8]> = smallvec![loc];
+                for i in 0..<$pt as SpatialPoint>::Dimension::USIZE {
+                    let extent = extents[i];
+                    if !wraps(extent) {
+                        continue;
+                    }
+                    // points across the edge are only closer than the ones inside up to half the extent away
+                    let shift =
+                        if two * loc[i] < extent && metric.axis_distance(i, loc[i]) < distance {
+                            extent
+                        } else if two * loc[i] >= extent
+                            && metric.axis_distance(i, extent - loc[i]) <= distance
+                        {
+                            -extent
+                        } else {
+                            continue;
+                        };
+                    for j in 0..images.len() {
+                        let mut image = images[j];
+                        image[i] += shift;
+                        images.push(image);
+                    }
+                }
+                images
+            }
+
+            /// Whether `image` is the location returned by [`images`](Self::images) which `point` is closest to.
+            fn is_nearest_image(&self, point: &$pt, image: &<$pt as SpatialPoint>::Vec) -> bool {
+                let Some(extents) = self.wrap else {
+                    return true;
+                };
+                let one: <$pt as SpatialPoint>::Scalar = One::one();
+                let two = one + one;
+
+                // doubled instead of halving the extent, which would round for integers
+                (0..<$pt as SpatialPoint>::Dimension::USIZE).all(|i| {
+                    let diff = two * (point.vec[i] - image[i]);
+                    !wraps(extents[i]) || (-extents[i] < diff && diff <= extents[i])
+                })
+            }
+
+            /// The boxes inside of the world covering the AABB from `min` to `max`, split at the wrapped edges.
+            fn aabb_images(
+                &self,
+                min: <$pt as SpatialPoint>::Vec,
+                max: <$pt as SpatialPoint>::Vec,
+            ) -> SmallVec<[(<$pt as SpatialPoint>::Vec, <$pt as SpatialPoint>::Vec); 8]> {
+                let mut boxes: SmallVec<[_; 8]> = smallvec![(min, max)];
+                let Some(extents) = self.wrap else {
+                    return boxes;
+                };
+                for i in 0..<$pt as SpatialPoint>::Dimension::USIZE {
+                    let extent = extents[i];
+                    if !wraps(extent) {
+                        continue;
+                    }
+                    let width = max[i] - min[i];
+                    let start = min[i].rem_euclid(extent);
+                    for j in 0..boxes.len() {
+                        if width >= extent {
+                            boxes[j].0[i] = Zero::zero();
+                            boxes[j].1[i] = extent;
+                            continue;
+                        }
+                        boxes[j].0[i] = start;
+                        boxes[j].1[i] = start + width;
+                        // the part sticking out of the world continues at its other edge
+                        if start + width >= extent {
+                            let mut image = boxes[j];
+                            image.0[i] = Zero::zero();
+                            image.1[i] = start + width - extent;
+                            boxes.push(image);
+                        }
+                    }
+                }
+                boxes
+            }
+
+            /// The squared distance between `a` and `b`, across the wrapped edges.
+            fn wrapped_distance_squared(
+                &self,
+                a: <$pt as SpatialPoint>::Vec,
+                b: <$pt as SpatialPoint>::Vec,
+            ) -> <$pt as SpatialPoint>::Scalar {
+                let Some(extents) = self.wrap else {
+                    return <$pt>::from(a).distance_squared(&b.into());
+                };
+                let mut diff = self.normalize(a) - self.normalize(b);
+                for i in 0..<$pt as SpatialPoint>::Dimension::USIZE {
+                    if wraps(extents[i]) {
+                        let d = diff[i].abs();
+                        diff[i] = if extents[i] - d < d {
+                            extents[i] - d
+                        } else {
+                            d
+                        };
+                    }
+                }
+                <$pt>::from(diff).distance_squared(&<$pt as SpatialPoint>::Vec::ZERO.into())
+            }
+
+            /// Whether a tree is currently being built in the background.
+            #[must_use]
+            pub fn is_rebuilding(&self) -> bool {
+                self.task.is_some()
+            }
+
+            /// Swaps in the tree built in the background, if it is finished. Returns whether it was swapped in.
+            ///
+            /// Called every frame by [`AutomaticUpdate`](crate::AutomaticUpdate) when using
+            /// [`AutomaticUpdate::with_async_rebuild`](crate::AutomaticUpdate::with_async_rebuild).
+            pub fn poll_rebuild(&mut self) -> bool {
+                let finished = self
+                    .task
+                    .as_mut()
+                    .and_then(|(generation, task)| Some((*generation, block_on(poll_once(task))?)));
+                let swapped = finished.is_some();
+                if let Some((generation, tree)) = finished {
+                    self.swap_tree(tree, generation);
+                    self.task = None;
+                }
+                if self.async_rebuild {
+                    self.rebuilt = swapped;
+                }
+                swapped
+            }
+
+            /// Call `f` for every pair of a point in this tree and a point in `other` which are within `distance` of each other.
+            ///
+            /// `other` usually tracks a different component, for example every enemy within range of a turret:
+            /// `turrets.dual_join_within_distance(&enemies, range, |turret, enemy| ...)`.
+            /// Descends both trees at once, skipping parts of the trees which are too far apart,
+            /// which is faster than searching `other` for every point like [`SpatialPairAccess::join_within_distance`] does.
+            ///
+            /// If either tree [wraps around](Self::with_wrap), `other` is searched around each point instead,
+            /// so distances are measured across the edges of `other`.
+            pub fn dual_join_within_distance<OtherComp>(
+                &self,
+                other: &$treename<OtherComp>,
+                distance: <$pt as SpatialPoint>::Scalar,
+                mut f: impl FnMut(&$pt, &$pt),
+            ) {
+                let _span = info_span!("dual-join-within-distance").entered();
+
+                if self.wrap.is_some() || other.wrap.is_some() {
+                    for a in self.tree.iter().filter(|a| self.is_live(a)) {
+                        other.for_each_live_within(a.vec, &Euclidean, squared(distance), |_, b| {
+                            f(a, b);
+                            ControlFlow::Continue(())
+                        });
+                    }
+                    return;
+                }
+
+                dual_join(
+                    KdNode::root(&self.tree),
+                    KdNode::root(&other.tree),
+                    squared(distance),
+                    &mut |a, b| {
+                        if self.is_live(a) && other.is_live(b) {
+                            f(a, b);
+                        }
+                    },
+                );
+            }
+
+            /// Whether `entity` was removed since the tree was last rebuilt.
+            ///
+            /// Removed entities stay in the tree until the next rebuild, but are skipped by all queries.
+            #[must_use]
+            pub fn is_removed(&self, entity: Entity) -> bool {
+                self.tombstones.contains_key(&entity)
+            }
+
+            /// Whether `point` can be returned by queries, as its entity wasn't removed since the last rebuild.
+            fn is_live(&self, point: &$pt) -> bool {
+                self.tombstones.is_empty()
+                    || point
+                        .entity
+                        .is_none_or(|e| !self.tombstones.contains_key(&e))
+            }
+
+            /// The `k` nearest live points to `loc` which pass `filter`, together with their distance as measured by `metric`.
+            fn nearest_points(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                metric: &impl Metric<$pt>,
+                max_distance: <$pt as SpatialPoint>::Scalar,
+                filter: impl FnMut(&$pt) -> bool,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, &$pt)> {
+                let mut found = Vec::with_capacity(k);
+                self.search_nearest(loc, k, metric, max_distance, filter, &mut found);
+                found
+            }
+
+            /// Collects the `k` nearest live points to `loc` which pass `filter` into `found`, as measured by `metric`.
+            fn search_nearest<'a>(
+                &'a self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                metric: &impl Metric<$pt>,
+                max_distance: <$pt as SpatialPoint>::Scalar,
+                mut filter: impl FnMut(&$pt) -> bool,
+                found: &mut impl NearestFound<<$pt as SpatialPoint>::Scalar, &'a $pt>,
+            ) {
+                if k == 0 {
+                    return;
+                }
+                for image in self.images(loc, metric, max_distance) {
+                    let search = KdNearest {
+                        loc: image.into(),
+                        k,
+                        metric,
+                        max_distance,
+                    };
+                    nearest_filtered(
+                        &self.tree,
+                        &search,
+                        0,
+                        &mut |point: &$pt| {
+                            self.is_live(point)
+                                && self.is_nearest_image(point, &image)
+                                && filter(point)
+                        },
+                        found,
+                    );
+                }
+            }
+
+            /// Calls `f` for every live point within `distance` of `loc` together with its distance, as measured by `metric`, until `f` breaks.
+            fn for_each_live_within(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                metric: &impl Metric<$pt>,
+                distance: <$pt as SpatialPoint>::Scalar,
+                mut f: impl FnMut(<$pt as SpatialPoint>::Scalar, &$pt) -> ControlFlow<()>,
+            ) {
+                for image in self.images(loc, metric, distance) {
+                    let p: $pt = image.into();
+                    let flow = try_for_each_within(&self.tree, &p, metric, distance, 0, &mut |e| {
+                        if self.is_live(e) && self.is_nearest_image(e, &image) {
+                            f(metric.distance(e, &p), e)
+                        } else {
+                            ControlFlow::Continue(())
+                        }
+                    });
+                    if flow.is_break() {
+                        return;
+                    }
+                }
+            }
+
+            /// Calls `f` with `a` and every live point within `distance_squared` of it which comes after `a` in the tree,
+            /// so each pair is only reported by one of its points. Finds points across the edges of wrapped trees.
+            ///
+            /// `a` has to be a point of the tree.
+            fn for_each_partner(
+                &self,
+                a: &$pt,
+                distance_squared: <$pt as SpatialPoint>::Scalar,
+                f: &mut impl FnMut(&$pt, &$pt),
+            ) {
+                self.for_each_live_within(a.vec, &Euclidean, distance_squared, |_, b| {
+                    if std::ptr::from_ref(a) < std::ptr::from_ref(b) {
+                        f(a, b);
+                    }
+                    ControlFlow::Continue(())
+                });
+            }
+
+            /// Replaces the tree with a rebuilt one,
+            /// forgetting entities removed before the rebuild started at `generation`.
+            fn swap_tree(&mut self, tree: BaseKdTree<$pt>, generation: u64) {
+                self.tree = tree;
+                self.tombstones.retain(|_, removed| *removed > generation);
+            }
+
+            fn build_tree(points: Vec<$pt>) -> BaseKdTree<$pt> {
+                KdScalar::build(points)
+            }
+        }
+
         impl<Comp> Default for $treename<Comp> {
             fn default() -> Self {
                 Self {
                     tree: default(),
+                    dirty: false,
+                    rebuilt: false,
+                    points: Vec::new(),
+                    async_rebuild: false,
+                    task: None,
+                    tombstones: default(),
+                    generation: 0,
+                    wrap: None,
                     component_type: PhantomData,
                 }
             }
         }
 
+        impl<Comp: TComp> AsyncRebuild for $treename<Comp> {
+            fn enable_async_rebuild(&mut self) {
+                self.async_rebuild = true;
+            }
+
+            fn poll_rebuild(&mut self) -> bool {
+                Self::poll_rebuild(self)
+            }
+        }
+
+        impl<Comp: TComp> WrapAround for $treename<Comp> {
+            fn set_wrap(&mut self, extents: DVec3) {
+                self.wrap = Some(VecFromCoordinate::from_coordinate(extents));
+            }
+        }
+
         impl<Comp> SpatialAccess for $treename<Comp>
         where
             Comp: TComp,
@@ -56,9 +802,9 @@ macro_rules! kdtree_impl {
 
             /// Get the nearest neighbour to a position.
             fn nearest_neighbour(&self, loc: <$pt as SpatialPoint>::Vec) -> Option<Self::ResultT> {
-                let p: $pt = loc.into();
-                let res = self.tree.nearest(&p);
-                res.map(|point| (point.item.vec(), point.item.entity()))
+                self.nearest_points(loc, 1, &Euclidean, Bounded::max_value(), |_| true)
+                    .first()
+                    .map(|(_, point)| (point.vec(), point.entity()))
             }
 
             /// Get the `k` neighbours to `loc`
@@ -70,12 +816,46 @@ macro_rules! kdtree_impl {
                 k: usize,
             ) -> Vec<Self::ResultT> {
                 let _span = info_span!("k-nearest").entered();
-                let p: $pt = loc.into();
 
-                self.tree
-                    .nearests(&p, k)
+                self.nearest_points(loc, k, &Euclidean, Bounded::max_value(), |_| true)
                     .iter()
-                    .map(|e| (e.item.vec(), e.item.entity()))
+                    .map(|(_, point)| (point.vec(), point.entity()))
+                    .collect()
+            }
+
+            /// Get the `k` neighbours to `loc` whose entities pass `filter`
+            ///
+            /// Keeps searching the tree until `k` points pass, skipping subtrees which can't contain closer points.
+            fn k_nearest_neighbour_filtered(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                mut filter: impl FnMut(Entity) -> bool,
+            ) -> Vec<Self::ResultT> {
+                let _span = info_span!("k-nearest-filtered").entered();
+
+                self.nearest_points(loc, k, &Euclidean, Bounded::max_value(), |point| {
+                    point.entity.is_some_and(&mut filter)
+                })
+                .iter()
+                .map(|(_, point)| (point.vec(), point.entity()))
+                .collect()
+            }
+
+            /// Get up to `k` neighbours to `loc` within `max_distance`
+            ///
+            /// Subtrees further away than `max_distance` are never searched.
+            fn k_nearest_within(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                max_distance: <$pt as SpatialPoint>::Scalar,
+            ) -> Vec<Self::ResultT> {
+                let _span = info_span!("k-nearest-within").entered();
+
+                self.nearest_points(loc, k, &Euclidean, squared(max_distance), |_| true)
+                    .iter()
+                    .map(|(_, point)| (point.vec(), point.entity()))
                     .collect()
             }
 
@@ -85,36 +865,273 @@ macro_rules! kdtree_impl {
                 loc: <$pt as SpatialPoint>::Vec,
                 distance: <$pt as SpatialPoint>::Scalar,
             ) -> Vec<Self::ResultT> {
+                let mut result = vec![];
+                self.within_distance_into(loc, distance, &mut result);
+                result
+            }
+
+            /// Call `f` for every entity within a certain distance (radius) of `loc`
+            fn for_each_within_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                distance: <$pt as SpatialPoint>::Scalar,
+                mut f: impl FnMut(Self::ResultT) -> ControlFlow<()>,
+            ) {
                 let _span = info_span!("within-distance").entered();
 
-                let distance: <$pt as KdPoint>::Scalar = distance.into();
+                self.for_each_live_within(loc, &Euclidean, squared(distance), |_, e| {
+                    f((e.vec(), e.entity()))
+                });
+            }
 
-                if self.tree.len() == 0 {
-                    vec![]
-                } else {
-                    let p: $pt = loc.into();
+            /// Append the `k` neighbours to `loc` to `out`
+            fn k_nearest_neighbour_into(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                out: &mut Vec<Self::ResultT>,
+            ) {
+                let _span = info_span!("k-nearest").entered();
+
+                let mut found =
+                    NearestInto::new(out, |vec| self.wrapped_distance_squared(loc, vec));
+                self.search_nearest(
+                    loc,
+                    k,
+                    &Euclidean,
+                    Bounded::max_value(),
+                    |_| true,
+                    &mut found,
+                );
+            }
+
+            /// Get the nearest neighbour to a position, together with its squared distance.
+            fn nearest_neighbour_with_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+            ) -> Option<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                self.nearest_points(loc, 1, &Euclidean, Bounded::max_value(), |_| true)
+                    .first()
+                    .map(|(distance, point)| (*distance, (point.vec(), point.entity())))
+            }
+
+            /// Get the `k` neighbours to `loc`, together with their squared distance.
+            fn k_nearest_neighbour_with_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let _span = info_span!("k-nearest").entered();
+
+                self.nearest_points(loc, k, &Euclidean, Bounded::max_value(), |_| true)
+                    .iter()
+                    .map(|(distance, point)| (*distance, (point.vec(), point.entity())))
+                    .collect()
+            }
+
+            /// Get the `k` neighbours to `loc` whose entities pass `filter`, together with their squared distance.
+            fn k_nearest_neighbour_filtered_with_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                mut filter: impl FnMut(Entity) -> bool,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let _span = info_span!("k-nearest-filtered").entered();
+
+                self.nearest_points(loc, k, &Euclidean, Bounded::max_value(), |point| {
+                    point.entity.is_some_and(&mut filter)
+                })
+                .iter()
+                .map(|(distance, point)| (*distance, (point.vec(), point.entity())))
+                .collect()
+            }
+
+            /// Get all entities within a certain distance (radius) of `loc`, together with their squared distance.
+            fn within_distance_with_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                distance: <$pt as SpatialPoint>::Scalar,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let _span = info_span!("within-distance").entered();
+
+                let mut result = vec![];
+                self.for_each_live_within(loc, &Euclidean, squared(distance), |distance, e| {
+                    result.push((distance, (e.vec(), e.entity())));
+                    ControlFlow::Continue(())
+                });
+                result
+            }
+        }
+
+        impl<Comp> SpatialPairAccess for $treename<Comp>
+        where
+            Comp: TComp,
+        {
+            fn iter_points(&self) -> impl Iterator<Item = &$pt> {
+                self.tree.iter().filter(|point| self.is_live(point))
+            }
+
+            /// Call `f` for every pair of points within `distance` of each other
+            ///
+            /// Searches the tree around each point.
+            fn for_each_pair_within_distance(
+                &self,
+                distance: <$pt as SpatialPoint>::Scalar,
+                mut f: impl FnMut(&$pt, &$pt),
+            ) {
+                let _span = info_span!("pairs-within-distance").entered();
 
-                    self.tree
-                        .within_radius(&p, distance)
-                        .iter()
-                        .map(|e| (e.vec(), e.entity()))
-                        .collect()
+                for a in self.iter_points() {
+                    self.for_each_partner(a, squared(distance), &mut f);
                 }
             }
+
+            /// Get every pair of points within `distance` of each other, searching around each point in parallel
+            fn pairs_within_distance_par(
+                &self,
+                distance: <$pt as SpatialPoint>::Scalar,
+            ) -> Vec<($pt, $pt)> {
+                let _span = info_span!("pairs-within-distance").entered();
+
+                batch(&self.tree, |a| {
+                    let mut pairs = vec![];
+                    if self.is_live(a) {
+                        self.for_each_partner(a, squared(distance), &mut |a, b| {
+                            pairs.push((*a, *b));
+                        });
+                    }
+                    pairs
+                })
+                .into_iter()
+                .flatten()
+                .collect()
+            }
         }
+
+        impl<Comp> SpatialMetricAccess for $treename<Comp>
+        where
+            Comp: TComp,
+        {
+            /// Get the `k` neighbours to `loc` as measured by `metric`, skipping subtrees which can't contain closer points.
+            fn k_nearest_neighbour_by(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                metric: &impl Metric<$pt>,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let _span = info_span!("k-nearest").entered();
+
+                self.nearest_points(loc, k, metric, Bounded::max_value(), |_| true)
+                    .iter()
+                    .map(|(distance, point)| (*distance, (point.vec(), point.entity())))
+                    .collect()
+            }
+
+            /// Get all entities within a certain distance of `loc` as measured by `metric`, together with their distance.
+            fn within_distance_by(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                distance: <$pt as SpatialPoint>::Scalar,
+                metric: &impl Metric<$pt>,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let _span = info_span!("within-distance").entered();
+
+                let mut result = vec![];
+                self.for_each_live_within(loc, metric, metric.radius(distance), |distance, e| {
+                    result.push((distance, (e.vec(), e.entity())));
+                    ControlFlow::Continue(())
+                });
+                result
+            }
+        }
+
+        impl<Comp> SpatialAABBAccess for $treename<Comp>
+        where
+            Comp: TComp,
+        {
+            /// Get all entities inside the axis-aligned bounding box spanned by `min` and `max`
+            fn within_aabb(
+                &self,
+                min: <$pt as SpatialPoint>::Vec,
+                max: <$pt as SpatialPoint>::Vec,
+            ) -> Vec<Self::ResultT> {
+                let _span = info_span!("within-aabb").entered();
+                let (a, b): ($pt, $pt) = (min.into(), max.into());
+                let (min, max): ($pt, $pt) = (a.min_point(&b).into(), a.max_point(&b).into());
+
+                let mut result = vec![];
+                for (min, max) in self.aabb_images(min.vec, max.vec) {
+                    let (min, max): ($pt, $pt) = (min.into(), max.into());
+                    for_each_in_aabb(&self.tree, &min, &max, 0, &mut |e| {
+                        if self.is_live(e) {
+                            result.push((e.vec(), e.entity()));
+                        }
+                    });
+                }
+                result
+            }
+        }
+
         impl<Comp: TComp> UpdateSpatialAccess for $treename<Comp> {
+            /// Rebuilds the tree, unless no point changed, was added or was removed since the last rebuild.
+            ///
+            /// With [`with_async_rebuild`](Self::with_async_rebuild) the tree is built in the background instead.
+            /// While it is being built, changes are kept track of for the next rebuild.
+            ///
+            /// Only returns `true` if the tree was rebuilt right away or `removed` was not empty,
+            /// trees built in the background are reported when they are swapped in.
             fn update(
                 &mut self,
                 data: impl Iterator<Item = (Self::Point, bool)>,
-                _: impl Iterator<Item = Entity>,
-            ) {
-                #[cfg(feature = "kdtree_rayon")]
-                let tree =
-                    KdTreeN::par_build_by_ordered_float(data.map(|(p, _)| p).collect::<Vec<_>>());
-                #[cfg(any(not(feature = "kdtree_rayon"), target_arch = "wasm32"))]
-                let tree =
-                    KdTreeN::build_by_ordered_float(data.map(|(p, _)| p).collect::<Vec<_>>());
-                self.tree = tree;
+                removed: impl Iterator<Item = Entity>,
+            ) -> bool {
+                let mut updated = false;
+                for entity in removed {
+                    self.remove_entity(entity);
+                    updated = true;
+                }
+                if self.task.is_some() {
+                    // the points are collected again once the running build is finished
+                    for (_, point_changed) in data {
+                        self.dirty |= point_changed;
+                    }
+                    return updated;
+                }
+
+                let mut changed = self.dirty;
+                self.points.clear();
+                self.points.extend(data.map(|(p, point_changed)| {
+                    changed |= point_changed;
+                    p
+                }));
+
+                // a different count means entities were added or removed without being reported
+                if !changed && self.points.len() == self.tree.len() {
+                    if !self.async_rebuild {
+                        self.rebuilt = false;
+                    }
+                    return updated;
+                }
+
+                let mut points = std::mem::take(&mut self.points);
+                for p in &mut points {
+                    p.vec = self.normalize(p.vec);
+                }
+                self.dirty = false;
+                let generation = self.generation;
+                self.generation += 1;
+                if self.async_rebuild {
+                    self.task = Some((
+                        generation,
+                        AsyncComputeTaskPool::get_or_init(TaskPool::default)
+                            .spawn(async move { KdScalar::build_sequential(points) }),
+                    ));
+                    updated
+                } else {
+                    self.swap_tree(Self::build_tree(points), generation);
+                    self.rebuilt = true;
+                    true
+                }
             }
 
             fn add(&mut self, _: Self::Point) {}
@@ -123,12 +1140,23 @@ macro_rules! kdtree_impl {
                 false
             }
 
-            fn remove_entity(&mut self, _: Entity) -> bool {
+            /// Marks the entity as removed, so queries skip it, and the tree for a rebuild on the next update, which drops it.
+            ///
+            /// Always returns `false`, as the entity stays in the tree until then.
+            fn remove_entity(&mut self, entity: Entity) -> bool {
+                self.tombstones.insert(entity, self.generation);
+                self.dirty = true;
                 false
             }
 
             fn clear(&mut self) {
                 self.tree = KdTreeN::default();
+                self.dirty = false;
+                self.rebuilt = false;
+                self.points.clear();
+                self.task = None;
+                self.tombstones.clear();
+                self.generation = 0;
             }
         }
     };
@@ -138,3 +1166,102 @@ kdtree_impl!(crate::point::Point3, KDTree3);
 kdtree_impl!(crate::point::Point3A, KDTree3A);
 kdtree_impl!(crate::point::PointD2, KDTreeD2);
 kdtree_impl!(crate::point::PointD3, KDTreeD3);
+kdtree_impl!(crate::point::IPoint2, KDTreeI2);
+kdtree_impl!(crate::point::IPoint3, KDTreeI3);
+kdtree_impl!(crate::point::I64Point2, KDTreeI64x2);
+kdtree_impl!(crate::point::I64Point3, KDTreeI64x3);
+
+macro_rules! kdtree_ray_impl {
+    ($pt:ty, $treename:ident) => {
+        impl<Comp> $treename<Comp> {
+            /// The offsets of the copies of the wrapped world which the AABB from `min` to `max` touches.
+            ///
+            /// `None` if the AABB is infinitely large along an axis which wraps.
+            #[allow(
+                clippy::cast_possible_truncation,
+                clippy::cast_precision_loss,
+                clippy::cast_sign_loss
+            )]
+            fn world_offsets(
+                &self,
+                min: <$pt as SpatialPoint>::Vec,
+                max: <$pt as SpatialPoint>::Vec,
+            ) -> Option<Vec<<$pt as SpatialPoint>::Vec>> {
+                let mut offsets = vec![<$pt as SpatialPoint>::Vec::ZERO];
+                let Some(extents) = self.wrap else {
+                    return Some(offsets);
+                };
+                for i in 0..<$pt as SpatialPoint>::Dimension::USIZE {
+                    let extent = extents[i];
+                    if !wraps(extent) {
+                        continue;
+                    }
+                    let first = (min[i] / extent).floor();
+                    let count = (max[i] / extent).floor() - first;
+                    if !count.is_finite() {
+                        return None;
+                    }
+                    offsets = offsets
+                        .into_iter()
+                        .flat_map(|offset| {
+                            (0..=count as usize).map(move |j| {
+                                let mut offset = offset;
+                                offset[i] = (first + j as <$pt as SpatialPoint>::Scalar) * extent;
+                                offset
+                            })
+                        })
+                        .collect();
+                }
+                Some(offsets)
+            }
+        }
+
+        impl<Comp> SpatialRayAccess for $treename<Comp>
+        where
+            Comp: TComp,
+        {
+            /// Get all entities within `radius` of a ray, sorted by their position along the ray
+            ///
+            /// On a [wrapped](Self::with_wrap) tree, the ray is followed through every copy of the world it passes,
+            /// returning the positions of the points inside of the world. Nothing is found if `max_t` is infinite.
+            fn cast_ray(
+                &self,
+                origin: <$pt as SpatialPoint>::Vec,
+                direction: <$pt as SpatialPoint>::Vec,
+                max_t: <$pt as SpatialPoint>::Scalar,
+                radius: <$pt as SpatialPoint>::Scalar,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let _span = info_span!("cast-ray").entered();
+                let end = origin + direction * max_t;
+                let Some(offsets) =
+                    self.world_offsets(origin.min(end) - radius, origin.max(end) + radius)
+                else {
+                    return vec![];
+                };
+
+                let mut hits = vec![];
+                for offset in offsets {
+                    let ray = KdRay::<$pt> {
+                        origin: (origin - offset).into(),
+                        direction: direction.into(),
+                        max_t,
+                        radius,
+                    };
+                    for_each_on_ray(&self.tree, &ray, (Zero::zero(), max_t), 0, &mut |t, e| {
+                        if self.is_live(e) {
+                            hits.push((t, (e.vec(), e.entity())));
+                        }
+                    });
+                }
+                sort_by_scalar(&mut hits);
+                hits
+            }
+        }
+    };
+}
+// the position along a ray is fractional, so only the floating point trees support ray casts
+kdtree_ray_impl!(crate::point::Point2, KDTree2);
+kdtree_ray_impl!(crate::point::Point3, KDTree3);
+kdtree_ray_impl!(crate::point::Point3A, KDTree3A);
+kdtree_ray_impl!(crate::point::PointD2, KDTreeD2);
+kdtree_ray_impl!(crate::point::PointD3, KDTreeD3);
@@ -35,19 +35,42 @@
 
 pub mod point;
 mod spatial_access;
-pub use self::spatial_access::SpatialAccess;
+pub use self::spatial_access::{
+    SpatialAABBAccess, SpatialAccess, SpatialMetricAccess, SpatialPairAccess, SpatialRayAccess,
+    UpdateSpatialAccess,
+};
+
+pub mod metric;
 
 use bevy::prelude::Component;
 mod timestep;
 pub use self::timestep::TimestepLength;
 
+mod spatial_query;
+pub use self::spatial_query::SpatialQuery;
+
+mod proximity;
+pub use self::proximity::{EnteredProximity, ExitedProximity, ProximityWatcher};
+
+mod neighbours;
+pub use self::neighbours::SpatialNeighbours;
+
 pub mod kdtree;
 
+pub mod grid;
+
+pub mod extent;
+
+pub mod linear;
+
+#[cfg(feature = "rstar")]
+pub mod rtree;
+
 mod plugin;
 pub use plugin::{SpatialStructure, *};
 
 mod automatic_systems;
-pub use automatic_systems::TransformMode;
+pub use automatic_systems::{CustomCoordinate, TransformMode};
 
 /// automatically implemented trait for all components which can be used as markers for automatic updates?
 pub trait TComp: Component + Send + Sync + 'static {}
@@ -0,0 +1,328 @@
+//! A naive, linear-scan spatial datastructure for ``bevy_spatial``.
+//!
+//! Points are stored in a flat [`Vec`] and every query looks at every point.
+//! For small numbers of entities (below ~100) this is faster than building and querying a tree.
+//! As it is trivially correct, it also serves as a reference to test the other datastructures against.
+
+use bevy::prelude::*;
+
+use crate::{
+    point::{in_aabb, ray_hit, squared, SpatialPoint},
+    spatial_access::{
+        insert_nearest, sort_by_scalar, NearestInto, SpatialAABBAccess, SpatialAccess,
+        SpatialPairAccess, SpatialRayAccess, UpdateSpatialAccess,
+    },
+    TComp,
+};
+
+use std::{cmp::Ordering, marker::PhantomData, ops::ControlFlow};
+
+macro_rules! linear_impl {
+    ($pt:ty, $linearname:ident) => {
+        /// Resource for storing a flat list of points.
+        #[derive(Resource)]
+        pub struct $linearname<Comp> {
+            /// The stored points, in no particular order.
+            pub points: Vec<$pt>,
+            component_type: PhantomData<Comp>,
+        }
+
+        impl<Comp> Default for $linearname<Comp> {
+            fn default() -> Self {
+                Self {
+                    points: default(),
+                    component_type: PhantomData,
+                }
+            }
+        }
+
+        impl<Comp> $linearname<Comp> {
+            /// The `k` points closest to `loc` which pass `filter`, together with their squared distance, sorted by distance.
+            fn nearest(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                mut filter: impl FnMut(&$pt) -> bool,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, &$pt)> {
+                let p: $pt = loc.into();
+                let mut found = Vec::with_capacity(k.min(self.points.len()));
+                for point in &self.points {
+                    if filter(point) {
+                        insert_nearest(&mut found, k, point.distance_squared(&p), point);
+                    }
+                }
+                found
+            }
+        }
+
+        impl<Comp> SpatialAccess for $linearname<Comp>
+        where
+            Comp: TComp,
+        {
+            type Point = $pt;
+
+            type Comp = Comp;
+            type ResultT = (<$pt as SpatialPoint>::Vec, Option<Entity>);
+
+            /// Get the nearest neighbour to a position.
+            fn nearest_neighbour(&self, loc: <$pt as SpatialPoint>::Vec) -> Option<Self::ResultT> {
+                let p: $pt = loc.into();
+                self.points
+                    .iter()
+                    .map(|point| (point.distance_squared(&p), point))
+                    .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal))
+                    .map(|(_, point)| (point.vec(), point.entity()))
+            }
+
+            /// Get the `k` neighbours to `loc`
+            ///
+            /// If `loc` is the location of a tracked entity, you might want to skip the first.
+            fn k_nearest_neighbour(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+            ) -> Vec<Self::ResultT> {
+                let _span = info_span!("k-nearest").entered();
+
+                self.nearest(loc, k, |_| true)
+                    .iter()
+                    .map(|(_, point)| (point.vec(), point.entity()))
+                    .collect()
+            }
+
+            /// Get the `k` neighbours to `loc` whose entities pass `filter`
+            fn k_nearest_neighbour_filtered(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                mut filter: impl FnMut(Entity) -> bool,
+            ) -> Vec<Self::ResultT> {
+                let _span = info_span!("k-nearest-filtered").entered();
+
+                self.nearest(loc, k, |point| point.entity.is_some_and(&mut filter))
+                    .iter()
+                    .map(|(_, point)| (point.vec(), point.entity()))
+                    .collect()
+            }
+
+            /// Get up to `k` neighbours to `loc` within `max_distance`
+            fn k_nearest_within(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                max_distance: <$pt as SpatialPoint>::Scalar,
+            ) -> Vec<Self::ResultT> {
+                let _span = info_span!("k-nearest-within").entered();
+                let p: $pt = loc.into();
+                let distance_squared = squared(max_distance);
+
+                let mut found = Vec::with_capacity(k);
+                for point in &self.points {
+                    let distance = point.distance_squared(&p);
+                    if distance <= distance_squared {
+                        insert_nearest(&mut found, k, distance, point);
+                    }
+                }
+                found
+                    .iter()
+                    .map(|(_, point)| (point.vec(), point.entity()))
+                    .collect()
+            }
+
+            /// Get all entities within a certain distance (radius) of `loc`
+            fn within_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                distance: <$pt as SpatialPoint>::Scalar,
+            ) -> Vec<Self::ResultT> {
+                let mut result = vec![];
+                self.within_distance_into(loc, distance, &mut result);
+                result
+            }
+
+            /// Call `f` for every entity within a certain distance (radius) of `loc`
+            fn for_each_within_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                distance: <$pt as SpatialPoint>::Scalar,
+                mut f: impl FnMut(Self::ResultT) -> ControlFlow<()>,
+            ) {
+                let _span = info_span!("within-distance").entered();
+                let p: $pt = loc.into();
+                let distance_squared = squared(distance);
+
+                let _ = self
+                    .points
+                    .iter()
+                    .filter(|point| point.distance_squared(&p) <= distance_squared)
+                    .try_for_each(|point| f((point.vec(), point.entity())));
+            }
+
+            /// Append the `k` neighbours to `loc` to `out`
+            fn k_nearest_neighbour_into(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                out: &mut Vec<Self::ResultT>,
+            ) {
+                let _span = info_span!("k-nearest").entered();
+                let p: $pt = loc.into();
+
+                let mut found = NearestInto::new(out, |vec| <$pt>::from(vec).distance_squared(&p));
+                for point in &self.points {
+                    insert_nearest(&mut found, k, point.distance_squared(&p), point);
+                }
+            }
+
+            /// Get the nearest neighbour to a position, together with its squared distance.
+            fn nearest_neighbour_with_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+            ) -> Option<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let p: $pt = loc.into();
+                self.points
+                    .iter()
+                    .map(|point| (point.distance_squared(&p), point))
+                    .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal))
+                    .map(|(distance, point)| (distance, (point.vec(), point.entity())))
+            }
+
+            /// Get the `k` neighbours to `loc`, together with their squared distance.
+            fn k_nearest_neighbour_with_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let _span = info_span!("k-nearest").entered();
+
+                self.nearest(loc, k, |_| true)
+                    .iter()
+                    .map(|(distance, point)| (*distance, (point.vec(), point.entity())))
+                    .collect()
+            }
+
+            /// Get the `k` neighbours to `loc` whose entities pass `filter`, together with their squared distance.
+            fn k_nearest_neighbour_filtered_with_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                mut filter: impl FnMut(Entity) -> bool,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let _span = info_span!("k-nearest-filtered").entered();
+
+                self.nearest(loc, k, |point| point.entity.is_some_and(&mut filter))
+                    .iter()
+                    .map(|(distance, point)| (*distance, (point.vec(), point.entity())))
+                    .collect()
+            }
+        }
+
+        impl<Comp> SpatialPairAccess for $linearname<Comp>
+        where
+            Comp: TComp,
+        {
+            fn iter_points(&self) -> impl Iterator<Item = &$pt> {
+                self.points.iter()
+            }
+        }
+
+        impl<Comp> SpatialRayAccess for $linearname<Comp>
+        where
+            Comp: TComp,
+        {
+            /// Get all entities within `radius` of a ray, sorted by their position along the ray
+            fn cast_ray(
+                &self,
+                origin: <$pt as SpatialPoint>::Vec,
+                direction: <$pt as SpatialPoint>::Vec,
+                max_t: <$pt as SpatialPoint>::Scalar,
+                radius: <$pt as SpatialPoint>::Scalar,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let _span = info_span!("cast-ray").entered();
+                let (o, d): ($pt, $pt) = (origin.into(), direction.into());
+
+                let mut hits: Vec<_> = self
+                    .points
+                    .iter()
+                    .filter_map(|point| {
+                        ray_hit(point, &o, &d, max_t, radius)
+                            .map(|t| (t, (point.vec(), point.entity())))
+                    })
+                    .collect();
+                sort_by_scalar(&mut hits);
+                hits
+            }
+        }
+
+        impl<Comp> SpatialAABBAccess for $linearname<Comp>
+        where
+            Comp: TComp,
+        {
+            /// Get all entities inside the axis-aligned bounding box spanned by `min` and `max`
+            fn within_aabb(
+                &self,
+                min: <$pt as SpatialPoint>::Vec,
+                max: <$pt as SpatialPoint>::Vec,
+            ) -> Vec<Self::ResultT> {
+                let _span = info_span!("within-aabb").entered();
+                let (a, b): ($pt, $pt) = (min.into(), max.into());
+                let (min, max): ($pt, $pt) = (a.min_point(&b).into(), a.max_point(&b).into());
+
+                self.points
+                    .iter()
+                    .filter(|point| in_aabb(*point, &min, &max))
+                    .map(|point| (point.vec(), point.entity()))
+                    .collect()
+            }
+        }
+
+        impl<Comp: TComp> UpdateSpatialAccess for $linearname<Comp> {
+            /// Replaces all points, as iterating all of them is needed to find the changed ones anyways.
+            fn update(
+                &mut self,
+                data: impl Iterator<Item = (Self::Point, bool)>,
+                _: impl Iterator<Item = Entity>,
+            ) -> bool {
+                let (len, mut updated) = (self.points.len(), false);
+                self.points.clear();
+                self.points.extend(data.map(|(p, changed)| {
+                    updated |= changed;
+                    p
+                }));
+                // a different count means entities were added or removed
+                updated || self.points.len() != len
+            }
+
+            /// Adds the point, replacing the previous points of the same [`Entity`] if there were any.
+            fn add(&mut self, point: Self::Point) {
+                if point.entity.is_some() {
+                    self.points.retain(|p| p.entity != point.entity);
+                }
+                self.points.push(point);
+            }
+
+            fn remove_point(&mut self, point: Self::Point) -> bool {
+                self.points
+                    .iter()
+                    .position(|p| *p == point)
+                    .map(|idx| self.points.swap_remove(idx))
+                    .is_some()
+            }
+
+            fn remove_entity(&mut self, entity: Entity) -> bool {
+                let len = self.points.len();
+                self.points.retain(|p| p.entity != Some(entity));
+                self.points.len() != len
+            }
+
+            fn clear(&mut self) {
+                self.points.clear();
+            }
+        }
+    };
+}
+linear_impl!(crate::point::Point2, Linear2);
+linear_impl!(crate::point::Point3, Linear3);
+linear_impl!(crate::point::Point3A, Linear3A);
+linear_impl!(crate::point::PointD2, LinearD2);
+linear_impl!(crate::point::PointD3, LinearD3);
@@ -0,0 +1,147 @@
+//! Distance metrics which can be used instead of the euclidean distance, see [`SpatialMetricAccess`](crate::SpatialMetricAccess).
+//!
+//! - [`Euclidean`] is the default straight line distance, measured squared like everywhere else in ``bevy_spatial``.
+//! - [`Manhattan`] is the sum of the distances along each axis, for grids where moving diagonally takes two steps.
+//! - [`Chebyshev`] is the largest distance along any axis, for grids where moving diagonally takes a single step.
+//! - [`WeightedEuclidean`] scales each axis, for example to make vertical distances count more than horizontal ones.
+//!
+//! For integer points, distances saturate at the largest value of the scalar instead of overflowing.
+//! The metrics can only be used with the [KD-trees](crate::kdtree), the other datastructures are euclidean only.
+
+use num_traits::{Signed, Zero};
+use typenum::Unsigned;
+
+use crate::point::{add_saturating, mul_saturating, squared, SpatialPoint};
+
+/// A way of measuring the distance between two points.
+///
+/// Distances don't have to be the actual distance, but have to grow with it, like the squared distance.
+pub trait Metric<P: SpatialPoint> {
+    /// The distance between `a` and `b`.
+    fn distance(&self, a: &P, b: &P) -> P::Scalar;
+
+    /// The distance between two points which are `diff` apart along `axis`, and equal along all other axes.
+    ///
+    /// Has to be at most the distance of any two points which are `diff` apart along `axis`,
+    /// as it is used to skip parts of datastructures which can't contain closer points.
+    fn axis_distance(&self, axis: usize, diff: P::Scalar) -> P::Scalar;
+
+    /// Convert a radius into the unit returned by [`distance`](Self::distance), for example by squaring it.
+    fn radius(&self, radius: P::Scalar) -> P::Scalar;
+}
+
+/// The straight line distance, measured squared.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Euclidean;
+
+impl<P: SpatialPoint> Metric<P> for Euclidean {
+    #[inline]
+    fn distance(&self, a: &P, b: &P) -> P::Scalar {
+        a.distance_squared(b)
+    }
+
+    #[inline]
+    fn axis_distance(&self, _: usize, diff: P::Scalar) -> P::Scalar {
+        squared(diff)
+    }
+
+    #[inline]
+    fn radius(&self, radius: P::Scalar) -> P::Scalar {
+        squared(radius)
+    }
+}
+
+/// The sum of the distances along each axis, also known as taxicab distance.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Manhattan;
+
+impl<P: SpatialPoint> Metric<P> for Manhattan {
+    fn distance(&self, a: &P, b: &P) -> P::Scalar {
+        (0..P::Dimension::USIZE).fold(P::Scalar::zero(), |sum, i| {
+            add_saturating(sum, (a.at(i) - b.at(i)).abs())
+        })
+    }
+
+    #[inline]
+    fn axis_distance(&self, _: usize, diff: P::Scalar) -> P::Scalar {
+        diff.abs()
+    }
+
+    #[inline]
+    fn radius(&self, radius: P::Scalar) -> P::Scalar {
+        radius
+    }
+}
+
+/// The largest distance along any axis, also known as chessboard distance.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Chebyshev;
+
+impl<P: SpatialPoint> Metric<P> for Chebyshev {
+    fn distance(&self, a: &P, b: &P) -> P::Scalar {
+        (0..P::Dimension::USIZE).fold(P::Scalar::zero(), |max, i| {
+            let diff = (a.at(i) - b.at(i)).abs();
+            if diff > max {
+                diff
+            } else {
+                max
+            }
+        })
+    }
+
+    #[inline]
+    fn axis_distance(&self, _: usize, diff: P::Scalar) -> P::Scalar {
+        diff.abs()
+    }
+
+    #[inline]
+    fn radius(&self, radius: P::Scalar) -> P::Scalar {
+        radius
+    }
+}
+
+/// The straight line distance with the squared distance along each axis multiplied by a weight, measured squared.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_spatial::metric::WeightedEuclidean;
+/// // vertical distances count double
+/// let metric = WeightedEuclidean::new(Vec3::new(1.0, 4.0, 1.0));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct WeightedEuclidean<V> {
+    /// The weight of each axis, should not be negative.
+    pub weights: V,
+}
+
+impl<V> WeightedEuclidean<V> {
+    /// Create a metric multiplying the squared distance along each axis with the corresponding weight.
+    pub fn new(weights: V) -> Self {
+        Self { weights }
+    }
+}
+
+impl<P> Metric<P> for WeightedEuclidean<P::Vec>
+where
+    P: SpatialPoint + From<P::Vec>,
+{
+    fn distance(&self, a: &P, b: &P) -> P::Scalar {
+        let weights: P = self.weights.into();
+        (0..P::Dimension::USIZE).fold(P::Scalar::zero(), |sum, i| {
+            add_saturating(
+                sum,
+                mul_saturating(weights.at(i), squared(a.at(i) - b.at(i))),
+            )
+        })
+    }
+
+    fn axis_distance(&self, axis: usize, diff: P::Scalar) -> P::Scalar {
+        let weights: P = self.weights.into();
+        mul_saturating(weights.at(axis), squared(diff))
+    }
+
+    #[inline]
+    fn radius(&self, radius: P::Scalar) -> P::Scalar {
+        squared(radius)
+    }
+}
@@ -0,0 +1,170 @@
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+use num_traits::{Bounded, FromPrimitive};
+use smallvec::SmallVec;
+
+use crate::{
+    automatic_systems::{CoordinateSource, GlamVec},
+    point::{
+        squared, SpatialPoint, VecFromCoordinate, VecFromGlobalTransform, VecFromTilePosition,
+        VecFromTransform,
+    },
+    SpatialAccess,
+};
+
+/// Component storing the neighbours of its entity among the entities tracked by the [`AutomaticUpdate`](crate::AutomaticUpdate) for `Comp`.
+///
+/// Filled in the [`SpatialSet`](crate::SpatialSet) after each update of the spatial datastructure,
+/// so systems ordered after it can read the neighbours with a plain [`Query`].
+/// The location of the entity is taken from the same source as the tracked entities,
+/// and the entity itself is never included, even when it is tracked too.
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_spatial::SpatialNeighbours;
+/// #[derive(Component)]
+/// struct Boid;
+///
+/// fn spawn_boid(mut commands: Commands) {
+///     commands.spawn((Boid, Transform::default(), SpatialNeighbours::<Boid>::nearest_within(7, 10.0)));
+/// }
+///
+/// fn flock(boids: Query<&SpatialNeighbours<Boid>>) {
+///     for neighbours in &boids {
+///         for &neighbour in neighbours.result() {
+///             // ...
+///         }
+///     }
+/// }
+/// # bevy::ecs::system::assert_is_system(spawn_boid);
+/// # bevy::ecs::system::assert_is_system(flock);
+/// ```
+#[derive(Component)]
+pub struct SpatialNeighbours<Comp> {
+    k: Option<usize>,
+    radius: Option<f32>,
+    result: SmallVec<[Entity; 8]>,
+    component_type: PhantomData<Comp>,
+}
+
+impl<Comp> SpatialNeighbours<Comp> {
+    /// Keep the `k` nearest neighbours.
+    #[must_use]
+    pub fn nearest(k: usize) -> Self {
+        Self::new(Some(k), None)
+    }
+
+    /// Keep all neighbours within `radius`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `radius` is negative or NaN.
+    #[must_use]
+    pub fn within(radius: f32) -> Self {
+        Self::new(None, Some(radius))
+    }
+
+    /// Keep up to `k` nearest neighbours within `radius`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `radius` is negative or NaN.
+    #[must_use]
+    pub fn nearest_within(k: usize, radius: f32) -> Self {
+        Self::new(Some(k), Some(radius))
+    }
+
+    fn new(k: Option<usize>, radius: Option<f32>) -> Self {
+        if let Some(radius) = radius {
+            assert!(
+                radius >= 0.0,
+                "the radius of SpatialNeighbours has to be a non-negative number, got {radius}"
+            );
+        }
+        Self {
+            k,
+            radius,
+            result: SmallVec::new(),
+            component_type: PhantomData,
+        }
+    }
+
+    /// The maximum number of neighbours, unlimited if `None`.
+    #[must_use]
+    pub fn k(&self) -> Option<usize> {
+        self.k
+    }
+
+    /// The maximum distance of neighbours, unlimited if `None`.
+    #[must_use]
+    pub fn radius(&self) -> Option<f32> {
+        self.radius
+    }
+
+    /// The neighbours found on the last update, closest first.
+    #[must_use]
+    pub fn result(&self) -> &[Entity] {
+        &self.result
+    }
+}
+
+/// Queries the neighbours of every entity with [`SpatialNeighbours`], only changing the component if they differ.
+#[allow(clippy::needless_pass_by_value, clippy::type_complexity)]
+pub(crate) fn update_neighbours<Source, SpatialDS>(
+    tree: Res<SpatialDS>,
+    mut neighbours: Query<(
+        Entity,
+        &mut SpatialNeighbours<SpatialDS::Comp>,
+        Source::Location,
+    )>,
+) where
+    Source: CoordinateSource,
+    SpatialDS: SpatialAccess<ResultT = (GlamVec<SpatialDS>, Option<Entity>)> + Resource,
+    SpatialDS::Point: From<(Entity, GlamVec<SpatialDS>)>,
+    GlamVec<SpatialDS>:
+        VecFromTransform + VecFromGlobalTransform + VecFromCoordinate + VecFromTilePosition,
+    <SpatialDS::Point as SpatialPoint>::Scalar: FromPrimitive,
+{
+    for (entity, mut neighbours, location) in &mut neighbours {
+        let loc = Source::location::<GlamVec<SpatialDS>>(&location);
+        // radii too large for integer scalars cover the whole datastructure
+        let radius = neighbours
+            .radius
+            .map(|radius| FromPrimitive::from_f32(radius).unwrap_or_else(Bounded::max_value));
+
+        // one more than needed, in case the entity finds itself
+        let result = match (neighbours.k, radius) {
+            (Some(k), None) => others(tree.k_nearest_entities(loc, k.saturating_add(1)), entity, k),
+            (Some(k), Some(radius)) => {
+                // the nearest entities are sorted by distance, so the ones within the radius come first
+                let max_distance = squared(radius);
+                let mut found = tree.k_nearest_entities(loc, k.saturating_add(1));
+                found.retain(|(distance, _)| *distance <= max_distance);
+                others(found, entity, k)
+            }
+            (None, Some(radius)) => others(
+                tree.within_distance_entities(loc, radius),
+                entity,
+                usize::MAX,
+            ),
+            (None, None) => SmallVec::new(),
+        };
+
+        if result != neighbours.result {
+            neighbours.result = result;
+        }
+    }
+}
+
+/// Up to `k` of the `found` entities, skipping `entity` itself.
+fn others<S, V>(
+    found: Vec<(S, (V, Option<Entity>))>,
+    entity: Entity,
+    k: usize,
+) -> SmallVec<[Entity; 8]> {
+    found
+        .into_iter()
+        .filter_map(|(_, (_, other))| other.filter(|&other| other != entity))
+        .take(k)
+        .collect()
+}
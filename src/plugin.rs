@@ -1,15 +1,37 @@
 use std::{marker::PhantomData, time::Duration};
 
 use bevy::{
-    ecs::schedule::{ScheduleLabel, SystemSet},
+    ecs::schedule::{InternedScheduleLabel, InternedSystemSet, ScheduleLabel, SystemSet},
+    math::DVec3,
     prelude::*,
 };
+use num_traits::FromPrimitive;
 
+#[cfg(feature = "rstar")]
+use crate::rtree::{RTree2, RTree3, RTree3A, RTreeD2, RTreeD3};
 use crate::{
-    automatic_systems::{AutoGT, AutoT, TransformMode},
-    kdtree::{KDTree2, KDTree3, KDTree3A},
+    automatic_systems::{
+        poll_rebuild, AutoC, AutoCell, AutoGT, AutoT, AutoTile, CoordinateSource, CustomCoordinate,
+        GlamVec, TransformMode,
+    },
+    extent::{
+        remove_extent_ds, update_extent_ds, ExtentLocation, ExtentTree2, ExtentTree3, ExtentTree3A,
+        ExtentTreeD2, ExtentTreeD3, SpatialExtent, UpdateExtentAccess,
+    },
+    grid::{Grid2, Grid3, Grid3A, GridD2, GridD3},
+    kdtree::{
+        AsyncRebuild, KDTree2, KDTree3, KDTree3A, KDTreeD2, KDTreeD3, KDTreeI2, KDTreeI3,
+        KDTreeI64x2, KDTreeI64x3, WrapAround,
+    },
+    linear::{Linear2, Linear3, Linear3A, LinearD2, LinearD3},
+    point::{
+        SpatialCoordinate, SpatialGridCell, SpatialPoint, SpatialTilePosition, VecFromCoordinate,
+        VecFromGlobalTransform, VecFromTilePosition, VecFromTransform,
+    },
+    proximity::{update_proximity, EnteredProximity, ExitedProximity},
+    spatial_access::UpdateSpatialAccess,
     timestep::{on_timer_changeable, TimestepLength},
-    TComp,
+    SpatialAccess, TComp,
 };
 
 /// Default set for spatial datastructure updates. Can be overridden using [`AutomaticUpdate::with_set()`](crate::AutomaticUpdate)
@@ -26,9 +48,84 @@ pub enum SpatialStructure {
     KDTree3,
     /// Corresponds to [`kdtree::KdTree3A`](crate::kdtree::KDTree3A)
     KDTree3A,
-    // Linear/naive (linfa?)
-    // Grid
-    // RStar
+    /// Corresponds to [`kdtree::KDTreeD2`](crate::kdtree::KDTreeD2)
+    KDTreeD2,
+    /// Corresponds to [`kdtree::KDTreeD3`](crate::kdtree::KDTreeD3)
+    KDTreeD3,
+    /// Corresponds to [`kdtree::KDTreeI2`](crate::kdtree::KDTreeI2)
+    KDTreeI2,
+    /// Corresponds to [`kdtree::KDTreeI3`](crate::kdtree::KDTreeI3)
+    KDTreeI3,
+    /// Corresponds to [`kdtree::KDTreeI64x2`](crate::kdtree::KDTreeI64x2)
+    KDTreeI64x2,
+    /// Corresponds to [`kdtree::KDTreeI64x3`](crate::kdtree::KDTreeI64x3)
+    KDTreeI64x3,
+    /// Corresponds to [`linear::Linear2`](crate::linear::Linear2)
+    Linear2,
+    /// Corresponds to [`linear::Linear3`](crate::linear::Linear3)
+    Linear3,
+    /// Corresponds to [`linear::Linear3A`](crate::linear::Linear3A)
+    Linear3A,
+    /// Corresponds to [`linear::LinearD2`](crate::linear::LinearD2)
+    LinearD2,
+    /// Corresponds to [`linear::LinearD3`](crate::linear::LinearD3)
+    LinearD3,
+    /// Corresponds to [`grid::Grid2`](crate::grid::Grid2), with the given cell size.
+    Grid2 {
+        /// The size of a single cell, should roughly match the distances used in queries.
+        cell_size: f32,
+    },
+    /// Corresponds to [`grid::Grid3`](crate::grid::Grid3), with the given cell size.
+    Grid3 {
+        /// The size of a single cell, should roughly match the distances used in queries.
+        cell_size: f32,
+    },
+    /// Corresponds to [`grid::Grid3A`](crate::grid::Grid3A), with the given cell size.
+    Grid3A {
+        /// The size of a single cell, should roughly match the distances used in queries.
+        cell_size: f32,
+    },
+    /// Corresponds to [`grid::GridD2`](crate::grid::GridD2), with the given cell size.
+    GridD2 {
+        /// The size of a single cell, should roughly match the distances used in queries.
+        cell_size: f64,
+    },
+    /// Corresponds to [`grid::GridD3`](crate::grid::GridD3), with the given cell size.
+    GridD3 {
+        /// The size of a single cell, should roughly match the distances used in queries.
+        cell_size: f64,
+    },
+    /// Corresponds to [`rtree::RTree2`](crate::rtree::RTree2)
+    #[cfg(feature = "rstar")]
+    RTree2,
+    /// Corresponds to [`rtree::RTree3`](crate::rtree::RTree3)
+    #[cfg(feature = "rstar")]
+    RTree3,
+    /// Corresponds to [`rtree::RTree3A`](crate::rtree::RTree3A)
+    #[cfg(feature = "rstar")]
+    RTree3A,
+    /// Corresponds to [`rtree::RTreeD2`](crate::rtree::RTreeD2)
+    #[cfg(feature = "rstar")]
+    RTreeD2,
+    /// Corresponds to [`rtree::RTreeD3`](crate::rtree::RTreeD3)
+    #[cfg(feature = "rstar")]
+    RTreeD3,
+}
+
+/// Enum containing the different types of extent datastructure compatible with [`AutomaticExtentUpdate`].
+#[derive(Copy, Clone, Default)]
+pub enum ExtentStructure {
+    /// Corresponds to [`extent::ExtentTree2`](crate::extent::ExtentTree2)
+    Extent2,
+    /// Corresponds to [`extent::ExtentTree3`](crate::extent::ExtentTree3)
+    #[default]
+    Extent3,
+    /// Corresponds to [`extent::ExtentTree3A`](crate::extent::ExtentTree3A)
+    Extent3A,
+    /// Corresponds to [`extent::ExtentTreeD2`](crate::extent::ExtentTreeD2)
+    ExtentD2,
+    /// Corresponds to [`extent::ExtentTreeD3`](crate::extent::ExtentTreeD3)
+    ExtentD3,
 }
 
 /// Plugin struct for setting up a spatial datastructure with automatic updating.
@@ -58,8 +155,13 @@ where
     pub(crate) frequency: Duration,
     pub(crate) transform: TransformMode,
     pub(crate) spatial_ds: SpatialStructure,
+    pub(crate) async_rebuild: bool,
+    pub(crate) wrap: Option<DVec3>,
 }
 
+/// Inserts the spatial datastructure and adds the systems keeping it updated, see [`build_spatial_ds`].
+pub(crate) type BuildFn = fn(&mut App, SpatialStructure, InternedScheduleLabel, InternedSystemSet);
+
 impl<Comp, Set: SystemSet, Schedule: ScheduleLabel + Clone> AutomaticUpdate<Comp, Set, Schedule> {
     /// Create a new [`AutomaticUpdate`] with defaults. Will add to the default [`ScheduleLabel`]: [`Update`].
     #[must_use]
@@ -71,6 +173,8 @@ impl<Comp, Set: SystemSet, Schedule: ScheduleLabel + Clone> AutomaticUpdate<Comp
             frequency: Duration::from_millis(50),
             transform: TransformMode::Transform,
             spatial_ds: default(),
+            async_rebuild: false,
+            wrap: None,
         }
     }
 
@@ -87,6 +191,8 @@ impl<Comp, Set: SystemSet, Schedule: ScheduleLabel + Clone> AutomaticUpdate<Comp
             frequency: self.frequency,
             transform: self.transform,
             spatial_ds: self.spatial_ds,
+            async_rebuild: self.async_rebuild,
+            wrap: self.wrap,
         }
     }
 
@@ -103,6 +209,8 @@ impl<Comp, Set: SystemSet, Schedule: ScheduleLabel + Clone> AutomaticUpdate<Comp
             frequency: self.frequency,
             transform: self.transform,
             spatial_ds: self.spatial_ds,
+            async_rebuild: self.async_rebuild,
+            wrap: self.wrap,
         }
     }
 
@@ -112,6 +220,38 @@ impl<Comp, Set: SystemSet, Schedule: ScheduleLabel + Clone> AutomaticUpdate<Comp
     /// - [`SpatialStructure::KDTree2`]
     /// - [`SpatialStructure::KDTree3`] (default)
     /// - [`SpatialStructure::KDTree3A`]
+    /// - [`SpatialStructure::KDTreeD2`]
+    /// - [`SpatialStructure::KDTreeD3`]
+    /// - [`SpatialStructure::KDTreeI2`]
+    /// - [`SpatialStructure::KDTreeI3`]
+    /// - [`SpatialStructure::KDTreeI64x2`]
+    /// - [`SpatialStructure::KDTreeI64x3`]
+    /// - [`SpatialStructure::Linear2`]
+    /// - [`SpatialStructure::Linear3`]
+    /// - [`SpatialStructure::Linear3A`]
+    /// - [`SpatialStructure::LinearD2`]
+    /// - [`SpatialStructure::LinearD3`]
+    /// - [`SpatialStructure::Grid2`]
+    /// - [`SpatialStructure::Grid3`]
+    /// - [`SpatialStructure::Grid3A`]
+    /// - [`SpatialStructure::GridD2`]
+    /// - [`SpatialStructure::GridD3`]
+    /// - [`SpatialStructure::RTree2`] (requires the `rstar` feature)
+    /// - [`SpatialStructure::RTree3`] (requires the `rstar` feature)
+    /// - [`SpatialStructure::RTree3A`] (requires the `rstar` feature)
+    /// - [`SpatialStructure::RTreeD2`] (requires the `rstar` feature)
+    /// - [`SpatialStructure::RTreeD3`] (requires the `rstar` feature)
+    ///
+    /// The double precision `D` variants are useful for very large worlds,
+    /// together with [`AutomaticUpdate::with_custom_coordinate`](Self::with_custom_coordinate)
+    /// or [`AutomaticUpdate::with_grid_cell`](Self::with_grid_cell) to keep the precision of the coordinates.
+    ///
+    /// The integer `I` variants fit tile and voxel worlds, see [`AutomaticUpdate::with_tile_position`](Self::with_tile_position).
+    /// Positions taken from transforms or coordinates are rounded to the nearest integer.
+    /// Coordinate differences must fit the scalar, so keep `i32` positions within ±2<sup>30</sup>.
+    /// Squared distances saturate instead of overflowing, so distances beyond 46340 for `i32` all compare equal.
+    ///
+    /// For entities with a size, use the extent datastructures of [`AutomaticExtentUpdate`] instead.
     #[must_use]
     pub fn with_spatial_ds(self, spatial_ds: SpatialStructure) -> Self {
         Self { spatial_ds, ..self }
@@ -130,6 +270,10 @@ impl<Comp, Set: SystemSet, Schedule: ScheduleLabel + Clone> AutomaticUpdate<Comp
     /// - [`TransformMode::Transform`] (default)
     /// - [`TransformMode::GlobalTransform`]
     ///
+    /// [`TransformMode::Custom`] is set by [`AutomaticUpdate::with_custom_coordinate`](Self::with_custom_coordinate),
+    /// [`AutomaticUpdate::with_grid_cell`](Self::with_grid_cell) or [`AutomaticUpdate::with_tile_position`](Self::with_tile_position)
+    /// instead, as it needs to know the component to use.
+    ///
     /// Note: using [`TransformMode::GlobalTransform`] might cause double frame-delays
     /// as Transform->GlobalTransform propagation happens in the
     /// [`TransformPropagate`](bevy::transform::TransformSystem::TransformPropagate) [`SystemSet`] in [`PostUpdate`](bevy::app::CoreSet::PostUpdate).
@@ -139,45 +283,529 @@ impl<Comp, Set: SystemSet, Schedule: ScheduleLabel + Clone> AutomaticUpdate<Comp
     pub fn with_transform(self, transform: TransformMode) -> Self {
         Self { transform, ..self }
     }
+
+    /// Build KD-trees in the background on the [`AsyncComputeTaskPool`](bevy::tasks::AsyncComputeTaskPool), instead of blocking the schedule.
+    ///
+    /// The points are copied on each update tick and the new tree is swapped in once it is finished,
+    /// queries keep using the previous tree until then.
+    /// This avoids frame spikes on rebuild ticks for very large trees, at the cost of results lagging a few frames behind.
+    ///
+    /// Also works on wasm, where the task pool is single threaded.
+    /// Only affects the KD-tree datastructures, the others are updated incrementally. Adding the plugin logs a warning for them.
+    #[must_use]
+    pub fn with_async_rebuild(self, async_rebuild: bool) -> Self {
+        Self {
+            async_rebuild,
+            ..self
+        }
+    }
+
+    /// Make positions wrap around at the edges of a world from zero to `extents`, like in asteroids.
+    ///
+    /// Positions are moved into the world when updating the datastructure, and nearest neighbour, distance, AABB,
+    /// pair, join and ray queries find entities across the edges.
+    /// Axes with an extent of zero or infinity don't wrap,
+    /// for example `DVec3::new(1000.0, 1000.0, 0.0)` wraps along x and y only.
+    /// The extents are kept in double precision for the double precision datastructures.
+    ///
+    /// Only the KD-tree datastructures can wrap around, see [`KDTree3::with_wrap`](crate::kdtree::KDTree3::with_wrap).
+    /// Adding the plugin panics for the other datastructures.
+    #[must_use]
+    pub fn with_wrap(self, extents: DVec3) -> Self {
+        Self {
+            wrap: Some(extents),
+            ..self
+        }
+    }
+
+    /// Extract coordinates from a custom component implementing [`SpatialCoordinate`] instead of a Transform.
+    ///
+    /// Sets [`TransformMode::Custom`]. Entities are updated whenever their `Coord` component changes.
+    ///
+    /// ```
+    /// # use bevy::{math::DVec3, prelude::*};
+    /// # use bevy_spatial::{point::SpatialCoordinate, AutomaticUpdate, SpatialStructure};
+    /// #[derive(Component)]
+    /// struct SimPosition(DVec3);
+    ///
+    /// impl SpatialCoordinate for SimPosition {
+    ///     fn coordinate(&self) -> DVec3 {
+    ///         self.0
+    ///     }
+    /// }
+    ///
+    /// #[derive(Component)]
+    /// struct EntityMarker;
+    ///
+    /// App::new().add_plugins(
+    ///     AutomaticUpdate::<EntityMarker>::new()
+    ///         .with_spatial_ds(SpatialStructure::KDTree3)
+    ///         .with_custom_coordinate::<SimPosition>(),
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_custom_coordinate<Coord: SpatialCoordinate>(self) -> Self
+    where
+        Comp: TComp,
+    {
+        Self {
+            transform: TransformMode::Custom(CustomCoordinate(
+                build_spatial_ds::<Comp, AutoC<Coord>>,
+            )),
+            ..self
+        }
+    }
+
+    /// Extract coordinates from a grid cell component implementing [`SpatialGridCell`] together with the [`Transform`],
+    /// which is the offset inside of that cell.
+    ///
+    /// This fits large worlds which place entities in cells to keep the single precision [`Transform`] precise.
+    /// Use one of the double precision datastructures, like [`SpatialStructure::KDTreeD3`], to keep the precision.
+    ///
+    /// Sets [`TransformMode::Custom`]. Entities are updated whenever their `Cell` component or their [`Transform`] changes.
+    #[must_use]
+    pub fn with_grid_cell<Cell: SpatialGridCell>(self) -> Self
+    where
+        Comp: TComp,
+    {
+        Self {
+            transform: TransformMode::Custom(CustomCoordinate(
+                build_spatial_ds::<Comp, AutoCell<Cell>>,
+            )),
+            ..self
+        }
+    }
+
+    /// Extract integer positions from a tile position component implementing [`SpatialTilePosition`].
+    ///
+    /// Meant for tile and voxel worlds together with one of the integer datastructures, like [`SpatialStructure::KDTreeI2`],
+    /// so positions are compared exactly. The third axis is dropped for two dimensional datastructures.
+    ///
+    /// Sets [`TransformMode::Custom`]. Entities are updated whenever their `Tile` component changes.
+    ///
+    /// ```
+    /// # use bevy::{math::I64Vec3, prelude::*};
+    /// # use bevy_spatial::{point::SpatialTilePosition, AutomaticUpdate, SpatialStructure};
+    /// #[derive(Component)]
+    /// struct TilePos(IVec2);
+    ///
+    /// impl SpatialTilePosition for TilePos {
+    ///     fn tile_position(&self) -> I64Vec3 {
+    ///         self.0.extend(0).as_i64vec3()
+    ///     }
+    /// }
+    ///
+    /// #[derive(Component)]
+    /// struct Unit;
+    ///
+    /// App::new().add_plugins(
+    ///     AutomaticUpdate::<Unit>::new()
+    ///         .with_spatial_ds(SpatialStructure::KDTreeI2)
+    ///         .with_tile_position::<TilePos>(),
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_tile_position<Tile: SpatialTilePosition>(self) -> Self
+    where
+        Comp: TComp,
+    {
+        Self {
+            transform: TransformMode::Custom(CustomCoordinate(
+                build_spatial_ds::<Comp, AutoTile<Tile>>,
+            )),
+            ..self
+        }
+    }
+}
+
+/// Insert the spatial datastructure and add the systems which keep it updated.
+fn insert_ds<Source, SpatialDS>(
+    app: &mut App,
+    spatial_ds: SpatialDS,
+    schedule: InternedScheduleLabel,
+    set: InternedSystemSet,
+) where
+    Source: CoordinateSource,
+    SpatialDS: UpdateSpatialAccess<ResultT = (GlamVec<SpatialDS>, Option<Entity>)> + Resource,
+    GlamVec<SpatialDS>:
+        VecFromTransform + VecFromGlobalTransform + VecFromCoordinate + VecFromTilePosition,
+    <SpatialDS as SpatialAccess>::Point: From<(Entity, GlamVec<SpatialDS>)>,
+    <<SpatialDS as SpatialAccess>::Point as SpatialPoint>::Scalar: FromPrimitive,
+{
+    app.insert_resource(spatial_ds);
+    Source::build::<SpatialDS>(app, schedule, set);
+    app.add_systems(
+        schedule,
+        update_proximity::<Source, SpatialDS>
+            .after(set)
+            .run_if(resource_changed::<SpatialDS>),
+    );
+}
+
+/// Insert the selected spatial datastructure and add the systems which keep it updated from `Source`.
+fn build_spatial_ds<Comp: TComp, Source: CoordinateSource>(
+    app: &mut App,
+    spatial_ds: SpatialStructure,
+    schedule: InternedScheduleLabel,
+    set: InternedSystemSet,
+) {
+    match spatial_ds {
+        SpatialStructure::KDTree2 => {
+            insert_ds::<Source, _>(app, KDTree2::<Comp>::default(), schedule, set);
+        }
+        SpatialStructure::KDTree3 => {
+            insert_ds::<Source, _>(app, KDTree3::<Comp>::default(), schedule, set);
+        }
+        SpatialStructure::KDTree3A => {
+            insert_ds::<Source, _>(app, KDTree3A::<Comp>::default(), schedule, set);
+        }
+        SpatialStructure::KDTreeD2 => {
+            insert_ds::<Source, _>(app, KDTreeD2::<Comp>::default(), schedule, set);
+        }
+        SpatialStructure::KDTreeD3 => {
+            insert_ds::<Source, _>(app, KDTreeD3::<Comp>::default(), schedule, set);
+        }
+        SpatialStructure::KDTreeI2 => {
+            insert_ds::<Source, _>(app, KDTreeI2::<Comp>::default(), schedule, set);
+        }
+        SpatialStructure::KDTreeI3 => {
+            insert_ds::<Source, _>(app, KDTreeI3::<Comp>::default(), schedule, set);
+        }
+        SpatialStructure::KDTreeI64x2 => {
+            insert_ds::<Source, _>(app, KDTreeI64x2::<Comp>::default(), schedule, set);
+        }
+        SpatialStructure::KDTreeI64x3 => {
+            insert_ds::<Source, _>(app, KDTreeI64x3::<Comp>::default(), schedule, set);
+        }
+        SpatialStructure::Linear2 => {
+            insert_ds::<Source, _>(app, Linear2::<Comp>::default(), schedule, set);
+        }
+        SpatialStructure::Linear3 => {
+            insert_ds::<Source, _>(app, Linear3::<Comp>::default(), schedule, set);
+        }
+        SpatialStructure::Linear3A => {
+            insert_ds::<Source, _>(app, Linear3A::<Comp>::default(), schedule, set);
+        }
+        SpatialStructure::LinearD2 => {
+            insert_ds::<Source, _>(app, LinearD2::<Comp>::default(), schedule, set);
+        }
+        SpatialStructure::LinearD3 => {
+            insert_ds::<Source, _>(app, LinearD3::<Comp>::default(), schedule, set);
+        }
+        SpatialStructure::Grid2 { cell_size } => {
+            insert_ds::<Source, _>(app, Grid2::<Comp>::new(cell_size), schedule, set);
+        }
+        SpatialStructure::Grid3 { cell_size } => {
+            insert_ds::<Source, _>(app, Grid3::<Comp>::new(cell_size), schedule, set);
+        }
+        SpatialStructure::Grid3A { cell_size } => {
+            insert_ds::<Source, _>(app, Grid3A::<Comp>::new(cell_size), schedule, set);
+        }
+        SpatialStructure::GridD2 { cell_size } => {
+            insert_ds::<Source, _>(app, GridD2::<Comp>::new(cell_size), schedule, set);
+        }
+        SpatialStructure::GridD3 { cell_size } => {
+            insert_ds::<Source, _>(app, GridD3::<Comp>::new(cell_size), schedule, set);
+        }
+        #[cfg(feature = "rstar")]
+        SpatialStructure::RTree2 => {
+            insert_ds::<Source, _>(app, RTree2::<Comp>::default(), schedule, set);
+        }
+        #[cfg(feature = "rstar")]
+        SpatialStructure::RTree3 => {
+            insert_ds::<Source, _>(app, RTree3::<Comp>::default(), schedule, set);
+        }
+        #[cfg(feature = "rstar")]
+        SpatialStructure::RTree3A => {
+            insert_ds::<Source, _>(app, RTree3A::<Comp>::default(), schedule, set);
+        }
+        #[cfg(feature = "rstar")]
+        SpatialStructure::RTreeD2 => {
+            insert_ds::<Source, _>(app, RTreeD2::<Comp>::default(), schedule, set);
+        }
+        #[cfg(feature = "rstar")]
+        SpatialStructure::RTreeD3 => {
+            insert_ds::<Source, _>(app, RTreeD3::<Comp>::default(), schedule, set);
+        }
+    }
+}
+
+/// Insert the extent datastructure and add the system which keeps it updated.
+fn insert_extent_ds<Loc, E, ExtentDS>(
+    app: &mut App,
+    schedule: InternedScheduleLabel,
+    set: InternedSystemSet,
+) where
+    Loc: ExtentLocation,
+    E: SpatialExtent,
+    ExtentDS: UpdateExtentAccess + Resource + Default,
+    ExtentDS::Vec: VecFromCoordinate,
+{
+    app.insert_resource(ExtentDS::default())
+        .add_systems(schedule, update_extent_ds::<Loc, E, ExtentDS>.in_set(set))
+        .add_systems(schedule, remove_extent_ds::<E, ExtentDS>.before(set));
 }
 
-impl<Comp: TComp, Set: SystemSet + Copy, Schedule: ScheduleLabel + Clone> Plugin for AutomaticUpdate<Comp, Set, Schedule> {
+/// Insert the selected extent datastructure with the size of entities taken from `E`.
+fn build_extent_ds<Comp: TComp, Loc: ExtentLocation, E: SpatialExtent>(
+    app: &mut App,
+    structure: ExtentStructure,
+    schedule: InternedScheduleLabel,
+    set: InternedSystemSet,
+) {
+    match structure {
+        ExtentStructure::Extent2 => {
+            insert_extent_ds::<Loc, E, ExtentTree2<Comp>>(app, schedule, set);
+        }
+        ExtentStructure::Extent3 => {
+            insert_extent_ds::<Loc, E, ExtentTree3<Comp>>(app, schedule, set);
+        }
+        ExtentStructure::Extent3A => {
+            insert_extent_ds::<Loc, E, ExtentTree3A<Comp>>(app, schedule, set);
+        }
+        ExtentStructure::ExtentD2 => {
+            insert_extent_ds::<Loc, E, ExtentTreeD2<Comp>>(app, schedule, set);
+        }
+        ExtentStructure::ExtentD3 => {
+            insert_extent_ds::<Loc, E, ExtentTreeD3<Comp>>(app, schedule, set);
+        }
+    }
+}
+
+/// Makes the datastructure build new trees in the background and polls them every frame.
+fn async_rebuild_ds<SpatialDS: AsyncRebuild>(
+    app: &mut App,
+    schedule: InternedScheduleLabel,
+    set: InternedSystemSet,
+) {
+    app.world_mut()
+        .resource_mut::<SpatialDS>()
+        .enable_async_rebuild();
+    app.add_systems(schedule, poll_rebuild::<SpatialDS>.before(set));
+}
+
+/// Enables background rebuilds for the KD-tree variants of [`SpatialStructure`].
+///
+/// Returns `false` for the other datastructures, which are updated incrementally and never rebuilt.
+fn enable_async_rebuild<Comp: TComp>(
+    app: &mut App,
+    spatial_ds: SpatialStructure,
+    schedule: InternedScheduleLabel,
+    set: InternedSystemSet,
+) -> bool {
+    match spatial_ds {
+        SpatialStructure::KDTree2 => async_rebuild_ds::<KDTree2<Comp>>(app, schedule, set),
+        SpatialStructure::KDTree3 => async_rebuild_ds::<KDTree3<Comp>>(app, schedule, set),
+        SpatialStructure::KDTree3A => async_rebuild_ds::<KDTree3A<Comp>>(app, schedule, set),
+        SpatialStructure::KDTreeD2 => async_rebuild_ds::<KDTreeD2<Comp>>(app, schedule, set),
+        SpatialStructure::KDTreeD3 => async_rebuild_ds::<KDTreeD3<Comp>>(app, schedule, set),
+        SpatialStructure::KDTreeI2 => async_rebuild_ds::<KDTreeI2<Comp>>(app, schedule, set),
+        SpatialStructure::KDTreeI3 => async_rebuild_ds::<KDTreeI3<Comp>>(app, schedule, set),
+        SpatialStructure::KDTreeI64x2 => async_rebuild_ds::<KDTreeI64x2<Comp>>(app, schedule, set),
+        SpatialStructure::KDTreeI64x3 => async_rebuild_ds::<KDTreeI64x3<Comp>>(app, schedule, set),
+        _ => return false,
+    }
+    true
+}
+
+/// Makes the KD-tree variants of [`SpatialStructure`] wrap around at `extents`.
+///
+/// Returns `false` for the other datastructures, which can't wrap around.
+fn enable_wrap<Comp: TComp>(app: &mut App, spatial_ds: SpatialStructure, extents: DVec3) -> bool {
+    fn wrap_ds<SpatialDS: WrapAround>(app: &mut App, extents: DVec3) {
+        app.world_mut()
+            .resource_mut::<SpatialDS>()
+            .set_wrap(extents);
+    }
+
+    match spatial_ds {
+        SpatialStructure::KDTree2 => wrap_ds::<KDTree2<Comp>>(app, extents),
+        SpatialStructure::KDTree3 => wrap_ds::<KDTree3<Comp>>(app, extents),
+        SpatialStructure::KDTree3A => wrap_ds::<KDTree3A<Comp>>(app, extents),
+        SpatialStructure::KDTreeD2 => wrap_ds::<KDTreeD2<Comp>>(app, extents),
+        SpatialStructure::KDTreeD3 => wrap_ds::<KDTreeD3<Comp>>(app, extents),
+        SpatialStructure::KDTreeI2 => wrap_ds::<KDTreeI2<Comp>>(app, extents),
+        SpatialStructure::KDTreeI3 => wrap_ds::<KDTreeI3<Comp>>(app, extents),
+        SpatialStructure::KDTreeI64x2 => wrap_ds::<KDTreeI64x2<Comp>>(app, extents),
+        SpatialStructure::KDTreeI64x3 => wrap_ds::<KDTreeI64x3<Comp>>(app, extents),
+        _ => return false,
+    }
+    true
+}
+
+impl<Comp: TComp, Set: SystemSet + Copy, Schedule: ScheduleLabel + Clone> Plugin
+    for AutomaticUpdate<Comp, Set, Schedule>
+{
     fn build(&self, app: &mut App) {
         app.insert_resource(TimestepLength(self.frequency, PhantomData::<Comp>))
+            .add_event::<EnteredProximity>()
+            .add_event::<ExitedProximity>()
             .configure_sets(
                 self.schedule.clone(),
                 self.set.run_if(on_timer_changeable::<Comp>),
             );
 
-        match self.spatial_ds {
-            SpatialStructure::KDTree2 => app.init_resource::<KDTree2<Comp>>(),
-            SpatialStructure::KDTree3 => app.init_resource::<KDTree3<Comp>>(),
-            SpatialStructure::KDTree3A => app.init_resource::<KDTree3A<Comp>>(),
+        let build: BuildFn = match self.transform {
+            TransformMode::Transform => build_spatial_ds::<Comp, AutoT>,
+            TransformMode::GlobalTransform => build_spatial_ds::<Comp, AutoGT>,
+            TransformMode::Custom(CustomCoordinate(build)) => build,
         };
+        build(
+            app,
+            self.spatial_ds,
+            self.schedule.intern(),
+            self.set.intern(),
+        );
+        if self.async_rebuild
+            && !enable_async_rebuild::<Comp>(
+                app,
+                self.spatial_ds,
+                self.schedule.intern(),
+                self.set.intern(),
+            )
+        {
+            warn!(
+                "AutomaticUpdate::with_async_rebuild only affects the KD-trees, the datastructure of {} is updated on the schedule",
+                std::any::type_name::<Comp>()
+            );
+        }
+        if let Some(extents) = self.wrap {
+            let wrapped = enable_wrap::<Comp>(app, self.spatial_ds, extents);
+            assert!(
+                wrapped,
+                "AutomaticUpdate::with_wrap only works with the KD-trees, the datastructure of {} can't wrap around",
+                std::any::type_name::<Comp>()
+            );
+        }
+    }
+}
+
+/// Plugin for setting up an extent datastructure with automatic updating, see [`extent`](crate::extent).
+///
+/// The size of tracked entities is taken from their `E` component, which is moved, rotated and scaled
+/// by the [`Transform`] or [`GlobalTransform`]. Entities without an `E` component are not tracked.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_spatial::{extent::{Extent, SpatialExtent}, AutomaticExtentUpdate, ExtentStructure};
+/// #[derive(Component)]
+/// struct Size(Vec3);
+///
+/// impl SpatialExtent for Size {
+///     fn extent(&self) -> Extent {
+///         Extent::cuboid(self.0 / 2.0)
+///     }
+/// }
+///
+/// #[derive(Component)]
+/// struct Boss;
+///
+/// App::new().add_plugins(
+///     AutomaticExtentUpdate::<Boss, Size>::new().with_spatial_ds(ExtentStructure::Extent3),
+/// );
+/// ```
+pub struct AutomaticExtentUpdate<Comp, E, Set = SpatialSet, Schedule = Update>
+where
+    Set: SystemSet,
+    Schedule: ScheduleLabel + Clone,
+{
+    pub(crate) comp: PhantomData<(Comp, E)>,
+    pub(crate) set: Set,
+    pub(crate) schedule: Schedule,
+    pub(crate) frequency: Duration,
+    pub(crate) transform: TransformMode,
+    pub(crate) spatial_ds: ExtentStructure,
+}
+
+impl<Comp, E, Set: SystemSet, Schedule: ScheduleLabel + Clone>
+    AutomaticExtentUpdate<Comp, E, Set, Schedule>
+{
+    /// Create a new [`AutomaticExtentUpdate`] with defaults. Will add to the default [`ScheduleLabel`]: [`Update`].
+    #[must_use]
+    pub fn new() -> AutomaticExtentUpdate<Comp, E> {
+        AutomaticExtentUpdate {
+            comp: PhantomData,
+            set: SpatialSet,
+            schedule: Update,
+            frequency: Duration::from_millis(50),
+            transform: TransformMode::Transform,
+            spatial_ds: default(),
+        }
+    }
+
+    /// Change the Bevy [`ScheduleLabel`] in which this plugin will put its systems.
+    pub fn with_schedule<NewSchedule: ScheduleLabel + Clone>(
+        self,
+        schedule: NewSchedule,
+    ) -> AutomaticExtentUpdate<Comp, E, Set, NewSchedule> {
+        AutomaticExtentUpdate {
+            set: self.set,
+            schedule,
+            comp: PhantomData,
+            frequency: self.frequency,
+            transform: self.transform,
+            spatial_ds: self.spatial_ds,
+        }
+    }
+
+    /// Change the Bevy [`SystemSet`] in which this plugin will put its systems.
+    pub fn with_set<NewSet: SystemSet + Copy>(
+        self,
+        set: NewSet,
+    ) -> AutomaticExtentUpdate<Comp, E, NewSet, Schedule> {
+        AutomaticExtentUpdate {
+            set,
+            schedule: self.schedule,
+            comp: PhantomData,
+            frequency: self.frequency,
+            transform: self.transform,
+            spatial_ds: self.spatial_ds,
+        }
+    }
+
+    /// Change which extent datastructure is used, [`ExtentStructure::Extent3`] by default.
+    #[must_use]
+    pub fn with_spatial_ds(self, spatial_ds: ExtentStructure) -> Self {
+        Self { spatial_ds, ..self }
+    }
+
+    /// Change the update rate.
+    ///
+    /// Expects a [Duration] which is the delay between updates.
+    #[must_use]
+    pub fn with_frequency(self, frequency: Duration) -> Self {
+        Self { frequency, ..self }
+    }
+
+    /// Change which Transform places the extents, [`TransformMode::Transform`] or [`TransformMode::GlobalTransform`].
+    ///
+    /// [`TransformMode::Custom`] can only be set by [`AutomaticUpdate`], the extents always need a transform.
+    #[must_use]
+    pub fn with_transform(self, transform: TransformMode) -> Self {
+        Self { transform, ..self }
+    }
+}
+
+impl<Comp: TComp, E: SpatialExtent, Set: SystemSet + Copy, Schedule: ScheduleLabel + Clone> Plugin
+    for AutomaticExtentUpdate<Comp, E, Set, Schedule>
+{
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TimestepLength(self.frequency, PhantomData::<Comp>))
+            .configure_sets(
+                self.schedule.clone(),
+                self.set.run_if(on_timer_changeable::<Comp>),
+            );
 
-        match self.transform {
-            TransformMode::Transform => match self.spatial_ds {
-                SpatialStructure::KDTree2 => {
-                    AutoT::<KDTree2<Comp>>::build(app, self.schedule.clone(), self.set);
-                }
-                SpatialStructure::KDTree3 => {
-                    AutoT::<KDTree3<Comp>>::build(app, self.schedule.clone(), self.set);
-                }
-                SpatialStructure::KDTree3A => {
-                    AutoT::<KDTree3A<Comp>>::build(app, self.schedule.clone(), self.set);
-                }
-            },
-            TransformMode::GlobalTransform => match self.spatial_ds {
-                SpatialStructure::KDTree2 => {
-                    AutoGT::<KDTree2<Comp>>::build(app, self.schedule.clone(), self.set);
-                }
-                SpatialStructure::KDTree3 => {
-                    AutoGT::<KDTree3<Comp>>::build(app, self.schedule.clone(), self.set);
-                }
-                SpatialStructure::KDTree3A => {
-                    AutoGT::<KDTree3A<Comp>>::build(app, self.schedule.clone(), self.set);
-                }
-            },
+        let build = if let TransformMode::GlobalTransform = self.transform {
+            build_extent_ds::<Comp, GlobalTransform, E>
+        } else {
+            build_extent_ds::<Comp, Transform, E>
         };
+        build(
+            app,
+            self.spatial_ds,
+            self.schedule.intern(),
+            self.set.intern(),
+        );
     }
 }
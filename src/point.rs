@@ -7,16 +7,58 @@
 //!   Needs a [`Entity`] to include in the Point type.
 //! - [`VecFromTransform`] and [`VecFromGlobalTransform`] used to extract the translation from the corresponding Transform.
 //!   Used for automatically updating the spatial datastructure.
-
-use bevy::{math::Vec3A, prelude::*};
-use num_traits::{Bounded, Num, Signed};
-use std::fmt::Debug;
+//! - [`SpatialCoordinate`] and [`VecFromCoordinate`] used to take the coordinates from a custom component instead.
+//!   [`SpatialGridCell`] does the same for a grid cell component combined with the [`Transform`] inside of that cell.
+//! - [`SpatialTilePosition`] and [`VecFromTilePosition`] used to take integer tile positions from a custom component.
+//! - [`SamplePoints`] tracks several points of a single entity instead of its position.
+
+use bevy::{
+    math::{DVec2, DVec3, I64Vec2, I64Vec3, Vec3A},
+    prelude::*,
+};
+use num_traits::{Bounded, Float, Num, Signed, Zero};
+use std::{fmt::Debug, ops::Sub};
 use typenum::Unsigned;
 
 /// Trait implemented for all numeric types used in Points.
 pub trait Scalar: Bounded + Num + Clone + Copy + Signed + PartialOrd + Debug {}
 impl<T> Scalar for T where T: Bounded + Num + Clone + Copy + Signed + PartialOrd + Debug {}
 
+/// Whether `S` is an integer type, whose division rounds towards zero.
+fn is_integer<S: Scalar>() -> bool {
+    S::one() / (S::one() + S::one()) == S::zero()
+}
+
+/// `x * x`, saturating at the largest value of integer scalars instead of overflowing.
+pub(crate) fn squared<S: Scalar>(x: S) -> S {
+    if is_integer::<S>() {
+        if x == S::min_value() {
+            return S::max_value();
+        }
+        let x = x.abs();
+        if x != S::zero() && x > S::max_value() / x {
+            return S::max_value();
+        }
+    }
+    x * x
+}
+
+/// `a * b` for non-negative `a` and `b`, saturating at the largest value of integer scalars instead of overflowing.
+pub(crate) fn mul_saturating<S: Scalar>(a: S, b: S) -> S {
+    if is_integer::<S>() && a != S::zero() && b > S::max_value() / a {
+        return S::max_value();
+    }
+    a * b
+}
+
+/// `a + b` for non-negative `a` and `b`, saturating at the largest value of integer scalars instead of overflowing.
+pub(crate) fn add_saturating<S: Scalar>(a: S, b: S) -> S {
+    if is_integer::<S>() && a > S::max_value() - b {
+        return S::max_value();
+    }
+    a + b
+}
+
 /// Represents a point in space and the Entity it was created from.
 ///
 /// Implements a bunch of common methods needed while working with these points in different spatial datastructures.
@@ -26,7 +68,7 @@ pub trait SpatialPoint: Copy + Clone + PartialEq + Debug {
     type Scalar: Scalar;
 
     /// The vector type itself, for example [`Vec3`](bevy::prelude::Vec3)
-    type Vec: Send + Sync + IntoSpatialPoint;
+    type Vec: Send + Sync + IntoSpatialPoint + Sub<Output = Self::Vec>;
 
     /// The dimension of this vector, like [`typenum::U2`] [`typenum::U3`]
     type Dimension: Unsigned;
@@ -38,6 +80,9 @@ pub trait SpatialPoint: Copy + Clone + PartialEq + Debug {
     fn at(&self, nth: usize) -> Self::Scalar;
 
     /// Get the squared distance of this point to another point of the same type.
+    ///
+    /// For integer points the squared distance saturates at the largest value instead of overflowing,
+    /// so all points further away than its square root, 46340 for `i32`, are equally far away.
     fn distance_squared(&self, other: &Self) -> Self::Scalar;
 
     /// Get the elementwise minimum between this and another point
@@ -53,6 +98,104 @@ pub trait SpatialPoint: Copy + Clone + PartialEq + Debug {
     fn vec(&self) -> Self::Vec;
 }
 
+/// Whether `point` lies inside the axis-aligned bounding box from `min` to `max`, including its boundary.
+pub(crate) fn in_aabb<P: SpatialPoint>(point: &P, min: &P, max: &P) -> bool {
+    (0..P::Dimension::USIZE).all(|i| min.at(i) <= point.at(i) && point.at(i) <= max.at(i))
+}
+
+/// Test `point` against the ray from `origin` along `direction`, limited to `0..=max_t`.
+///
+/// Returns the `t` of the point on the ray closest to `point`, if `point` is within `radius` of the ray.
+/// Only for floating point scalars, as `t` is fractional.
+pub(crate) fn ray_hit<P: SpatialPoint>(
+    point: &P,
+    origin: &P,
+    direction: &P,
+    max_t: P::Scalar,
+    radius: P::Scalar,
+) -> Option<P::Scalar>
+where
+    P::Scalar: Float,
+{
+    let zero = P::Scalar::zero();
+    let (mut along, mut length_squared) = (zero, zero);
+    for i in 0..P::Dimension::USIZE {
+        along = along + (point.at(i) - origin.at(i)) * direction.at(i);
+        length_squared = length_squared + direction.at(i) * direction.at(i);
+    }
+
+    let mut t = if length_squared == zero {
+        zero
+    } else {
+        along / length_squared
+    };
+    if t < zero {
+        t = zero;
+    } else if t > max_t {
+        t = max_t;
+    }
+
+    let mut distance_squared = zero;
+    for i in 0..P::Dimension::USIZE {
+        let d = point.at(i) - (origin.at(i) + direction.at(i) * t);
+        distance_squared = distance_squared + d * d;
+    }
+    (distance_squared <= radius * radius).then_some(t)
+}
+
+/// Narrow the range of `t` to the part of the ray which is below (or above) `bound` along a single axis.
+///
+/// Returns [`None`] if no part of the ray in the range is.
+#[cfg(any(feature = "kdtree", feature = "rstar"))]
+pub(crate) fn clip_ray<S: Float>(
+    origin: S,
+    direction: S,
+    bound: S,
+    below: bool,
+    (t0, t1): (S, S),
+) -> Option<(S, S)> {
+    if direction == S::zero() {
+        let inside = if below {
+            origin <= bound
+        } else {
+            origin >= bound
+        };
+        return inside.then_some((t0, t1));
+    }
+    let t = (bound - origin) / direction;
+    let (t0, t1) = if below == (direction > S::zero()) {
+        (t0, if t < t1 { t } else { t1 })
+    } else {
+        (if t > t0 { t } else { t0 }, t1)
+    };
+    (t0 <= t1).then_some((t0, t1))
+}
+
+/// Whether the ray from `origin` along `direction`, limited to `0..=max_t`, passes within `radius` of the box from `min` to `max`.
+///
+/// Tests against the box grown by `radius`, so it can report hits for rays which only pass near its corners.
+#[cfg(feature = "rstar")]
+pub(crate) fn ray_near_aabb<P: SpatialPoint>(
+    origin: &P,
+    direction: &P,
+    max_t: P::Scalar,
+    radius: P::Scalar,
+    min: &P,
+    max: &P,
+) -> bool
+where
+    P::Scalar: Float,
+{
+    let mut range = Some((P::Scalar::zero(), max_t));
+    for i in 0..P::Dimension::USIZE {
+        let (o, d) = (origin.at(i), direction.at(i));
+        range = range
+            .and_then(|range| clip_ray(o, d, max.at(i) + radius, true, range))
+            .and_then(|range| clip_ray(o, d, min.at(i) - radius, false, range));
+    }
+    range.is_some()
+}
+
 /// Trait implemented for vector coordinate types for which a corresponding Point type exists.
 /// Used to convert from the vector coordinate types to the corresponding Point type by providing a Entity
 #[allow(clippy::module_name_repetitions)]
@@ -70,6 +213,16 @@ pub trait IntoSpatialPoint: Send + Sync + Sized + Copy {
 }
 macro_rules! impl_spatial_point {
     ($pointname:ident, $bvec:ty, $unit:ty, $dim:ty, $diml:literal) => {
+        impl_spatial_point!(
+            $pointname,
+            $bvec,
+            $unit,
+            $dim,
+            $diml,
+            |a: $bvec, b: $bvec| { a.distance_squared(b) }
+        );
+    };
+    ($pointname:ident, $bvec:ty, $unit:ty, $dim:ty, $diml:literal, $distance_squared:expr) => {
         /// Newtype over bevy/glam vectors, needed to allow implementing foreign spatial datastructure traits.
         #[derive(Clone, Copy, Debug, Default, PartialEq)]
         pub struct $pointname {
@@ -104,7 +257,7 @@ macro_rules! impl_spatial_point {
 
             #[inline]
             fn distance_squared(&self, other: &Self) -> Self::Scalar {
-                self.vec.distance_squared(other.vec)
+                $distance_squared(self.vec, other.vec)
             }
 
             #[inline]
@@ -157,6 +310,50 @@ impl_spatial_point!(Point3, bevy::math::Vec3, f32, typenum::consts::U3, 3);
 impl_spatial_point!(Point3A, bevy::math::Vec3A, f32, typenum::consts::U3, 3);
 impl_spatial_point!(PointD2, bevy::math::DVec2, f64, typenum::consts::U2, 2);
 impl_spatial_point!(PointD3, bevy::math::DVec3, f64, typenum::consts::U3, 3);
+impl_spatial_point!(
+    IPoint2,
+    bevy::math::IVec2,
+    i32,
+    typenum::consts::U2,
+    2,
+    saturating_distance_squared
+);
+impl_spatial_point!(
+    IPoint3,
+    bevy::math::IVec3,
+    i32,
+    typenum::consts::U3,
+    3,
+    saturating_distance_squared
+);
+impl_spatial_point!(
+    I64Point2,
+    bevy::math::I64Vec2,
+    i64,
+    typenum::consts::U2,
+    2,
+    saturating_distance_squared
+);
+impl_spatial_point!(
+    I64Point3,
+    bevy::math::I64Vec3,
+    i64,
+    typenum::consts::U3,
+    3,
+    saturating_distance_squared
+);
+
+/// The squared distance between two integer vectors, saturating instead of overflowing.
+fn saturating_distance_squared<V, S, const N: usize>(a: V, b: V) -> S
+where
+    V: Into<[S; N]>,
+    S: Scalar,
+{
+    let (a, b) = (a.into(), b.into());
+    (0..N).fold(S::zero(), |sum, i| {
+        add_saturating(sum, squared(a[i] - b[i]))
+    })
+}
 
 /// Helper trait for extracting the translation of a [`Transform`] to a specific vector type
 /// Used for automatically updating the spatial datastructure.
@@ -180,10 +377,39 @@ impl VecFromTransform for Vec3A {
         t.translation.into()
     }
 }
+impl VecFromTransform for DVec2 {
+    fn from_transform(t: &Transform) -> Self {
+        t.translation.truncate().as_dvec2()
+    }
+}
+impl VecFromTransform for DVec3 {
+    fn from_transform(t: &Transform) -> Self {
+        t.translation.as_dvec3()
+    }
+}
+impl VecFromTransform for IVec2 {
+    fn from_transform(t: &Transform) -> Self {
+        t.translation.truncate().round().as_ivec2()
+    }
+}
+impl VecFromTransform for IVec3 {
+    fn from_transform(t: &Transform) -> Self {
+        t.translation.round().as_ivec3()
+    }
+}
+impl VecFromTransform for I64Vec2 {
+    fn from_transform(t: &Transform) -> Self {
+        t.translation.truncate().round().as_i64vec2()
+    }
+}
+impl VecFromTransform for I64Vec3 {
+    fn from_transform(t: &Transform) -> Self {
+        t.translation.round().as_i64vec3()
+    }
+}
 
 /// Helper trait for extracting the translation of a [`GlobalTransform`] to a specific vector type
 /// Used for automatically updating the spatial datastructure.
-
 pub trait VecFromGlobalTransform: IntoSpatialPoint {
     /// Create this vector type from a [`GlobalTransform`]
     fn from_transform(t: &GlobalTransform) -> Self;
@@ -204,3 +430,231 @@ impl VecFromGlobalTransform for Vec3A {
         t.translation().into()
     }
 }
+impl VecFromGlobalTransform for DVec2 {
+    fn from_transform(t: &GlobalTransform) -> Self {
+        t.translation().truncate().as_dvec2()
+    }
+}
+impl VecFromGlobalTransform for DVec3 {
+    fn from_transform(t: &GlobalTransform) -> Self {
+        t.translation().as_dvec3()
+    }
+}
+impl VecFromGlobalTransform for IVec2 {
+    fn from_transform(t: &GlobalTransform) -> Self {
+        t.translation().truncate().round().as_ivec2()
+    }
+}
+impl VecFromGlobalTransform for IVec3 {
+    fn from_transform(t: &GlobalTransform) -> Self {
+        t.translation().round().as_ivec3()
+    }
+}
+impl VecFromGlobalTransform for I64Vec2 {
+    fn from_transform(t: &GlobalTransform) -> Self {
+        t.translation().truncate().round().as_i64vec2()
+    }
+}
+impl VecFromGlobalTransform for I64Vec3 {
+    fn from_transform(t: &GlobalTransform) -> Self {
+        t.translation().round().as_i64vec3()
+    }
+}
+
+/// Trait for components which provide the coordinates of their entity, instead of a [`Transform`].
+///
+/// Used with [`TransformMode::Custom`](crate::TransformMode::Custom), see [`AutomaticUpdate::with_custom_coordinate`](crate::AutomaticUpdate::with_custom_coordinate).
+/// Whenever the component changes, the entity is updated in the spatial datastructure.
+///
+/// ```
+/// # use bevy::{math::DVec3, prelude::*};
+/// # use bevy_spatial::point::SpatialCoordinate;
+/// #[derive(Component)]
+/// struct SimPosition(DVec3);
+///
+/// impl SpatialCoordinate for SimPosition {
+///     fn coordinate(&self) -> DVec3 {
+///         self.0
+///     }
+/// }
+/// ```
+pub trait SpatialCoordinate: Component {
+    /// The coordinates of this entity, in double precision so no datastructure loses precision.
+    fn coordinate(&self) -> DVec3;
+}
+
+/// Trait for components which place their entity in a cell of a large grid, like the grid cells of `big_space`.
+///
+/// The [`Transform`] of the entity is the offset inside of the cell.
+/// Used with [`AutomaticUpdate::with_grid_cell`](crate::AutomaticUpdate::with_grid_cell).
+///
+/// ```
+/// # use bevy::{math::DVec3, prelude::*};
+/// # use bevy_spatial::point::SpatialGridCell;
+/// #[derive(Component)]
+/// struct GridCell(IVec3);
+///
+/// impl SpatialGridCell for GridCell {
+///     fn cell_origin(&self) -> DVec3 {
+///         self.0.as_dvec3() * 10_000.0
+///     }
+/// }
+/// ```
+pub trait SpatialGridCell: Component {
+    /// The position of the origin of this cell.
+    fn cell_origin(&self) -> DVec3;
+}
+
+/// Helper trait for converting the coordinates of a [`SpatialCoordinate`] or [`SpatialGridCell`] to a specific vector type
+/// Used for automatically updating the spatial datastructure.
+pub trait VecFromCoordinate: IntoSpatialPoint {
+    /// Create this vector type from the coordinates of a [`SpatialCoordinate`]
+    fn from_coordinate(c: DVec3) -> Self;
+}
+
+impl VecFromCoordinate for Vec2 {
+    fn from_coordinate(c: DVec3) -> Self {
+        c.truncate().as_vec2()
+    }
+}
+impl VecFromCoordinate for Vec3 {
+    fn from_coordinate(c: DVec3) -> Self {
+        c.as_vec3()
+    }
+}
+impl VecFromCoordinate for Vec3A {
+    fn from_coordinate(c: DVec3) -> Self {
+        c.as_vec3().into()
+    }
+}
+impl VecFromCoordinate for DVec2 {
+    fn from_coordinate(c: DVec3) -> Self {
+        c.truncate()
+    }
+}
+impl VecFromCoordinate for DVec3 {
+    fn from_coordinate(c: DVec3) -> Self {
+        c
+    }
+}
+impl VecFromCoordinate for IVec2 {
+    fn from_coordinate(c: DVec3) -> Self {
+        c.truncate().round().as_ivec2()
+    }
+}
+impl VecFromCoordinate for IVec3 {
+    fn from_coordinate(c: DVec3) -> Self {
+        c.round().as_ivec3()
+    }
+}
+impl VecFromCoordinate for I64Vec2 {
+    fn from_coordinate(c: DVec3) -> Self {
+        c.truncate().round().as_i64vec2()
+    }
+}
+impl VecFromCoordinate for I64Vec3 {
+    fn from_coordinate(c: DVec3) -> Self {
+        c.round().as_i64vec3()
+    }
+}
+
+/// Trait for components which place their entity on an integer tile or voxel position, instead of a [`Transform`].
+///
+/// Used with [`AutomaticUpdate::with_tile_position`](crate::AutomaticUpdate::with_tile_position),
+/// together with the integer datastructures like [`KDTreeI2`](crate::kdtree::KDTreeI2) the position is never rounded.
+/// Whenever the component changes, the entity is updated in the spatial datastructure.
+///
+/// ```
+/// # use bevy::{math::I64Vec3, prelude::*};
+/// # use bevy_spatial::point::SpatialTilePosition;
+/// #[derive(Component)]
+/// struct Tile(IVec2);
+///
+/// impl SpatialTilePosition for Tile {
+///     fn tile_position(&self) -> I64Vec3 {
+///         self.0.extend(0).as_i64vec3()
+///     }
+/// }
+/// ```
+pub trait SpatialTilePosition: Component {
+    /// The tile position of this entity, in 64 bit so no datastructure loses precision.
+    fn tile_position(&self) -> I64Vec3;
+}
+
+/// Helper trait for converting the position of a [`SpatialTilePosition`] to a specific vector type
+/// Used for automatically updating the spatial datastructure.
+pub trait VecFromTilePosition: IntoSpatialPoint {
+    /// Create this vector type from the position of a [`SpatialTilePosition`]
+    fn from_tile_position(p: I64Vec3) -> Self;
+}
+
+impl VecFromTilePosition for Vec2 {
+    fn from_tile_position(p: I64Vec3) -> Self {
+        p.truncate().as_vec2()
+    }
+}
+impl VecFromTilePosition for Vec3 {
+    fn from_tile_position(p: I64Vec3) -> Self {
+        p.as_vec3()
+    }
+}
+impl VecFromTilePosition for Vec3A {
+    fn from_tile_position(p: I64Vec3) -> Self {
+        p.as_vec3().into()
+    }
+}
+impl VecFromTilePosition for DVec2 {
+    fn from_tile_position(p: I64Vec3) -> Self {
+        p.truncate().as_dvec2()
+    }
+}
+impl VecFromTilePosition for DVec3 {
+    fn from_tile_position(p: I64Vec3) -> Self {
+        p.as_dvec3()
+    }
+}
+impl VecFromTilePosition for IVec2 {
+    fn from_tile_position(p: I64Vec3) -> Self {
+        p.truncate().as_ivec2()
+    }
+}
+impl VecFromTilePosition for IVec3 {
+    fn from_tile_position(p: I64Vec3) -> Self {
+        p.as_ivec3()
+    }
+}
+impl VecFromTilePosition for I64Vec2 {
+    fn from_tile_position(p: I64Vec3) -> Self {
+        p.truncate()
+    }
+}
+impl VecFromTilePosition for I64Vec3 {
+    fn from_tile_position(p: I64Vec3) -> Self {
+        p
+    }
+}
+
+/// Component with points in the local space of its entity, which are tracked instead of the position of the entity.
+///
+/// With [`TransformMode::Transform`](crate::TransformMode::Transform) or [`TransformMode::GlobalTransform`](crate::TransformMode::GlobalTransform),
+/// every point is moved, rotated and scaled by the transform and added to the datastructure with the same [`Entity`].
+/// This fits long entities like trains or snakes, which should be found near any of their parts.
+/// With [`TransformMode::Custom`](crate::TransformMode::Custom) the points are added to the coordinate,
+/// for [`SpatialGridCell`] they are transformed inside of the cell, and for [`SpatialTilePosition`] they are given in tiles.
+/// Without any points, the position of the entity is tracked as usual.
+///
+/// Queries like [`SpatialAccess::k_nearest_entities`](crate::SpatialAccess::k_nearest_entities) return every entity only once.
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_spatial::point::SamplePoints;
+/// #[derive(Component)]
+/// struct Train;
+///
+/// fn spawn_train(mut commands: Commands) {
+///     let wagons = (0..5).map(|i| Vec3::new(0.0, 0.0, i as f32 * -10.0)).collect();
+///     commands.spawn((Train, Transform::default(), SamplePoints(wagons)));
+/// }
+/// # bevy::ecs::system::assert_is_system(spawn_train);
+/// ```
+#[derive(Component, Clone, Debug, Default, PartialEq)]
+pub struct SamplePoints(pub Vec<Vec3>);
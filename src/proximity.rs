@@ -0,0 +1,147 @@
+use std::{marker::PhantomData, ops::ControlFlow};
+
+use bevy::{ecs::entity::EntityHashSet, prelude::*};
+use num_traits::FromPrimitive;
+
+use crate::{
+    automatic_systems::{CoordinateSource, GlamVec},
+    point::{
+        SpatialPoint, VecFromCoordinate, VecFromGlobalTransform, VecFromTilePosition,
+        VecFromTransform,
+    },
+    SpatialAccess,
+};
+
+/// Component which makes its entity watch for entities tracked by the [`AutomaticUpdate`](crate::AutomaticUpdate) for `Comp` getting within `radius`.
+///
+/// After each update of the spatial datastructure the entities within `radius` are compared to the previous update,
+/// sending [`EnteredProximity`] and [`ExitedProximity`] both as events and as observer triggers targeting the watcher.
+/// The watcher itself is never reported, even when it is tracked too.
+///
+/// The location of the watcher is taken from the same source as the tracked entities,
+/// for example its [`Transform`] when using [`TransformMode::Transform`](crate::TransformMode::Transform).
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_spatial::{EnteredProximity, ProximityWatcher};
+/// #[derive(Component)]
+/// struct Enemy;
+///
+/// fn spawn_trap(mut commands: Commands) {
+///     commands
+///         .spawn((Transform::default(), ProximityWatcher::<Enemy>::new(5.0)))
+///         .observe(|trigger: Trigger<EnteredProximity>| {
+///             info!("{} stepped into the trap", trigger.other);
+///         });
+/// }
+/// # bevy::ecs::system::assert_is_system(spawn_trap);
+/// ```
+#[derive(Component)]
+pub struct ProximityWatcher<Comp> {
+    radius: f32,
+    inside: EntityHashSet,
+    component_type: PhantomData<Comp>,
+}
+
+impl<Comp> ProximityWatcher<Comp> {
+    /// Create a watcher for tracked entities within `radius`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `radius` is negative or NaN.
+    #[must_use]
+    pub fn new(radius: f32) -> Self {
+        assert!(
+            radius >= 0.0,
+            "the radius of ProximityWatcher has to be a non-negative number, got {radius}"
+        );
+        Self {
+            radius,
+            inside: default(),
+            component_type: PhantomData,
+        }
+    }
+
+    /// The distance within which tracked entities are reported.
+    #[must_use]
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// The entities which were within `radius` on the last update.
+    pub fn inside(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.inside.iter().copied()
+    }
+}
+
+/// Sent when a tracked entity got within the radius of a [`ProximityWatcher`].
+///
+/// Also triggered for observers of the watcher entity.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EnteredProximity {
+    /// The entity with the [`ProximityWatcher`].
+    pub watcher: Entity,
+    /// The tracked entity which got within the radius.
+    pub other: Entity,
+}
+
+/// Sent when a tracked entity left the radius of a [`ProximityWatcher`], or stopped being tracked.
+///
+/// Also triggered for observers of the watcher entity.
+/// As this is only noticed on the next update, `other` might be despawned already.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExitedProximity {
+    /// The entity with the [`ProximityWatcher`].
+    pub watcher: Entity,
+    /// The tracked entity which left the radius.
+    pub other: Entity,
+}
+
+/// Compares the entities within the radius of each watcher to the previous update and sends the differences.
+///
+/// Runs after each update which changed the datastructure.
+#[allow(clippy::needless_pass_by_value, clippy::type_complexity)]
+pub(crate) fn update_proximity<Source, SpatialDS>(
+    mut commands: Commands,
+    tree: Res<SpatialDS>,
+    mut watchers: Query<(
+        Entity,
+        &mut ProximityWatcher<SpatialDS::Comp>,
+        Source::Location,
+    )>,
+    mut entered: EventWriter<EnteredProximity>,
+    mut exited: EventWriter<ExitedProximity>,
+) where
+    Source: CoordinateSource,
+    SpatialDS: SpatialAccess<ResultT = (GlamVec<SpatialDS>, Option<Entity>)> + Resource,
+    GlamVec<SpatialDS>:
+        VecFromTransform + VecFromGlobalTransform + VecFromCoordinate + VecFromTilePosition,
+    <SpatialDS::Point as SpatialPoint>::Scalar: FromPrimitive,
+{
+    for (watcher, mut proximity, location) in &mut watchers {
+        let Some(radius) = FromPrimitive::from_f32(proximity.radius) else {
+            continue;
+        };
+        let loc = Source::location::<GlamVec<SpatialDS>>(&location);
+
+        let mut inside = EntityHashSet::default();
+        tree.for_each_within_distance(loc, radius, |(_, entity)| {
+            if let Some(other) = entity.filter(|&other| other != watcher) {
+                inside.insert(other);
+            }
+            ControlFlow::Continue(())
+        });
+        if inside == proximity.inside {
+            continue;
+        }
+
+        for &other in inside.difference(&proximity.inside) {
+            entered.send(EnteredProximity { watcher, other });
+            commands.trigger_targets(EnteredProximity { watcher, other }, watcher);
+        }
+        for &other in proximity.inside.difference(&inside) {
+            exited.send(ExitedProximity { watcher, other });
+            commands.trigger_targets(ExitedProximity { watcher, other }, watcher);
+        }
+        proximity.inside = inside;
+    }
+}
@@ -0,0 +1,463 @@
+//! implementations to use [`rstar`] R*-trees as a spatial datastructure in ``bevy_spatial``.
+//!
+//! Unlike the [`kdtree`](crate::kdtree) trees, which are fully rebuilt on every update,
+//! these trees are updated incrementally: only points which changed, were added or were removed are touched.
+//! This makes them a good fit for large, mostly static sets of entities.
+
+use bevy::{ecs::entity::EntityHashMap, prelude::*};
+use num_traits::Float;
+use rstar::{Point as RStarPoint, RTree as BaseRTree, SelectionFunction, AABB};
+use smallvec::{smallvec, SmallVec};
+use typenum::Unsigned;
+
+use crate::{
+    point::{ray_hit, ray_near_aabb, squared, SpatialPoint},
+    spatial_access::{
+        sort_by_scalar, SpatialAABBAccess, SpatialAccess, SpatialPairAccess, SpatialRayAccess,
+        UpdateSpatialAccess,
+    },
+    TComp,
+};
+
+use std::{marker::PhantomData, ops::ControlFlow};
+
+/// Selects the nodes of an ``RTree`` which the swept volume of a ray passes through, see [`SpatialRayAccess::cast_ray`].
+struct RaySelection<P: SpatialPoint> {
+    origin: P,
+    direction: P,
+    max_t: P::Scalar,
+    radius: P::Scalar,
+}
+
+impl<P> SelectionFunction<P> for RaySelection<P>
+where
+    P: SpatialPoint + RStarPoint,
+    <P as SpatialPoint>::Scalar: Float,
+{
+    fn should_unpack_parent(&self, envelope: &AABB<P>) -> bool {
+        ray_near_aabb(
+            &self.origin,
+            &self.direction,
+            self.max_t,
+            self.radius,
+            &envelope.lower(),
+            &envelope.upper(),
+        )
+    }
+}
+
+macro_rules! rtree_impl {
+    ($pt:ty, $treename:ident) => {
+        impl RStarPoint for $pt {
+            type Scalar = <$pt as SpatialPoint>::Scalar;
+
+            const DIMENSIONS: usize = <<$pt as SpatialPoint>::Dimension as Unsigned>::USIZE;
+
+            fn generate(generator: impl FnMut(usize) -> Self::Scalar) -> Self {
+                <<$pt as SpatialPoint>::Vec>::from_array(std::array::from_fn(generator)).into()
+            }
+
+            fn nth(&self, index: usize) -> Self::Scalar {
+                self.vec[index]
+            }
+
+            fn nth_mut(&mut self, index: usize) -> &mut Self::Scalar {
+                &mut self.vec[index]
+            }
+        }
+
+        /// Resource for storing a ``RTree``
+        ///
+        /// Keeps track of which points belong to which [`Entity`], so moved and removed entities can be updated in place.
+        #[derive(Resource)]
+        pub struct $treename<Comp> {
+            tree: BaseRTree<$pt>,
+            entities: EntityHashMap<SmallVec<[$pt; 1]>>,
+            component_type: PhantomData<Comp>,
+        }
+
+        impl<Comp> $treename<Comp> {
+            /// The underlying ``RTree``
+            #[must_use]
+            pub fn tree(&self) -> &BaseRTree<$pt> {
+                &self.tree
+            }
+
+            /// Adds another point of an entity, keeping its previous points.
+            fn add_sample(&mut self, point: $pt) {
+                if let Some(entity) = point.entity {
+                    self.entities.entry(entity).or_default().push(point);
+                }
+                self.tree.insert(point);
+            }
+        }
+
+        impl<Comp> Default for $treename<Comp> {
+            fn default() -> Self {
+                Self {
+                    tree: default(),
+                    entities: default(),
+                    component_type: PhantomData,
+                }
+            }
+        }
+
+        impl<Comp> SpatialAccess for $treename<Comp>
+        where
+            Comp: TComp,
+        {
+            type Point = $pt;
+
+            type Comp = Comp;
+            type ResultT = (<$pt as SpatialPoint>::Vec, Option<Entity>);
+
+            /// Get the nearest neighbour to a position.
+            fn nearest_neighbour(&self, loc: <$pt as SpatialPoint>::Vec) -> Option<Self::ResultT> {
+                let p: $pt = loc.into();
+                self.tree
+                    .nearest_neighbor(&p)
+                    .map(|point| (point.vec(), point.entity()))
+            }
+
+            /// Get the `k` neighbours to `loc`
+            ///
+            /// If `loc` is the location of a tracked entity, you might want to skip the first.
+            fn k_nearest_neighbour(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+            ) -> Vec<Self::ResultT> {
+                let _span = info_span!("k-nearest").entered();
+                let p: $pt = loc.into();
+
+                self.tree
+                    .nearest_neighbor_iter(&p)
+                    .take(k)
+                    .map(|e| (e.vec(), e.entity()))
+                    .collect()
+            }
+
+            /// Get the `k` neighbours to `loc` whose entities pass `filter`
+            fn k_nearest_neighbour_filtered(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                mut filter: impl FnMut(Entity) -> bool,
+            ) -> Vec<Self::ResultT> {
+                let _span = info_span!("k-nearest-filtered").entered();
+                let p: $pt = loc.into();
+
+                self.tree
+                    .nearest_neighbor_iter(&p)
+                    .filter(|e| e.entity.is_some_and(&mut filter))
+                    .take(k)
+                    .map(|e| (e.vec(), e.entity()))
+                    .collect()
+            }
+
+            /// Get up to `k` neighbours to `loc` within `max_distance`
+            fn k_nearest_within(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                max_distance: <$pt as SpatialPoint>::Scalar,
+            ) -> Vec<Self::ResultT> {
+                let _span = info_span!("k-nearest-within").entered();
+                let p: $pt = loc.into();
+                let distance_squared = squared(max_distance);
+
+                self.tree
+                    .nearest_neighbor_iter_with_distance_2(&p)
+                    .take_while(|(_, distance)| *distance <= distance_squared)
+                    .take(k)
+                    .map(|(e, _)| (e.vec(), e.entity()))
+                    .collect()
+            }
+
+            /// Get all entities within a certain distance (radius) of `loc`
+            fn within_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                distance: <$pt as SpatialPoint>::Scalar,
+            ) -> Vec<Self::ResultT> {
+                let mut result = vec![];
+                self.within_distance_into(loc, distance, &mut result);
+                result
+            }
+
+            /// Call `f` for every entity within a certain distance (radius) of `loc`
+            fn for_each_within_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                distance: <$pt as SpatialPoint>::Scalar,
+                mut f: impl FnMut(Self::ResultT) -> ControlFlow<()>,
+            ) {
+                let _span = info_span!("within-distance").entered();
+                let p: $pt = loc.into();
+
+                let _ = self
+                    .tree
+                    .locate_within_distance(p, squared(distance))
+                    .try_for_each(|e| f((e.vec(), e.entity())));
+            }
+
+            /// Append the `k` neighbours to `loc` to `out`
+            fn k_nearest_neighbour_into(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                out: &mut Vec<Self::ResultT>,
+            ) {
+                let _span = info_span!("k-nearest").entered();
+                let p: $pt = loc.into();
+
+                out.extend(
+                    self.tree
+                        .nearest_neighbor_iter(&p)
+                        .take(k)
+                        .map(|e| (e.vec(), e.entity())),
+                );
+            }
+
+            /// Call `f` for each of the `k` neighbours to `loc`, finding them one at a time without allocating
+            fn for_each_k_nearest_neighbour(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                mut f: impl FnMut(Self::ResultT) -> ControlFlow<()>,
+            ) {
+                let _span = info_span!("k-nearest").entered();
+                let p: $pt = loc.into();
+
+                let _ = self
+                    .tree
+                    .nearest_neighbor_iter(&p)
+                    .take(k)
+                    .try_for_each(|e| f((e.vec(), e.entity())));
+            }
+
+            /// Get the nearest neighbour to a position, together with its squared distance.
+            fn nearest_neighbour_with_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+            ) -> Option<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let p: $pt = loc.into();
+                self.tree
+                    .nearest_neighbor_iter_with_distance_2(&p)
+                    .next()
+                    .map(|(point, distance)| (distance, (point.vec(), point.entity())))
+            }
+
+            /// Get the `k` neighbours to `loc`, together with their squared distance.
+            fn k_nearest_neighbour_with_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let _span = info_span!("k-nearest").entered();
+                let p: $pt = loc.into();
+
+                self.tree
+                    .nearest_neighbor_iter_with_distance_2(&p)
+                    .take(k)
+                    .map(|(e, distance)| (distance, (e.vec(), e.entity())))
+                    .collect()
+            }
+
+            /// Get the `k` neighbours to `loc` whose entities pass `filter`, together with their squared distance.
+            fn k_nearest_neighbour_filtered_with_distance(
+                &self,
+                loc: <$pt as SpatialPoint>::Vec,
+                k: usize,
+                mut filter: impl FnMut(Entity) -> bool,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let _span = info_span!("k-nearest-filtered").entered();
+                let p: $pt = loc.into();
+
+                self.tree
+                    .nearest_neighbor_iter_with_distance_2(&p)
+                    .filter(|(e, _)| e.entity.is_some_and(&mut filter))
+                    .take(k)
+                    .map(|(e, distance)| (distance, (e.vec(), e.entity())))
+                    .collect()
+            }
+        }
+
+        impl<Comp> SpatialPairAccess for $treename<Comp>
+        where
+            Comp: TComp,
+        {
+            fn iter_points(&self) -> impl Iterator<Item = &$pt> {
+                self.tree.iter()
+            }
+
+            /// Call `f` for every pair of points within `distance` of each other
+            ///
+            /// Looks up the neighbours of each point in the tree.
+            fn for_each_pair_within_distance(
+                &self,
+                distance: <$pt as SpatialPoint>::Scalar,
+                mut f: impl FnMut(&$pt, &$pt),
+            ) {
+                let _span = info_span!("pairs-within-distance").entered();
+
+                for a in self.tree.iter() {
+                    for b in self.tree.locate_within_distance(*a, squared(distance)) {
+                        if std::ptr::from_ref(a) < std::ptr::from_ref(b) {
+                            f(a, b);
+                        }
+                    }
+                }
+            }
+        }
+
+        impl<Comp> SpatialRayAccess for $treename<Comp>
+        where
+            Comp: TComp,
+        {
+            /// Get all entities within `radius` of a ray, sorted by their position along the ray
+            ///
+            /// Only descends into nodes whose box the ray passes within `radius` of.
+            fn cast_ray(
+                &self,
+                origin: <$pt as SpatialPoint>::Vec,
+                direction: <$pt as SpatialPoint>::Vec,
+                max_t: <$pt as SpatialPoint>::Scalar,
+                radius: <$pt as SpatialPoint>::Scalar,
+            ) -> Vec<(<$pt as SpatialPoint>::Scalar, Self::ResultT)> {
+                let _span = info_span!("cast-ray").entered();
+                let (o, d): ($pt, $pt) = (origin.into(), direction.into());
+                let selection = RaySelection {
+                    origin: o,
+                    direction: d,
+                    max_t,
+                    radius,
+                };
+
+                let mut hits: Vec<_> = self
+                    .tree
+                    .locate_with_selection_function(selection)
+                    .filter_map(|e| {
+                        ray_hit(e, &o, &d, max_t, radius).map(|t| (t, (e.vec(), e.entity())))
+                    })
+                    .collect();
+                sort_by_scalar(&mut hits);
+                hits
+            }
+        }
+
+        impl<Comp> SpatialAABBAccess for $treename<Comp>
+        where
+            Comp: TComp,
+        {
+            /// Get all entities inside the axis-aligned bounding box spanned by `min` and `max`
+            fn within_aabb(
+                &self,
+                min: <$pt as SpatialPoint>::Vec,
+                max: <$pt as SpatialPoint>::Vec,
+            ) -> Vec<Self::ResultT> {
+                let _span = info_span!("within-aabb").entered();
+                let aabb = AABB::from_corners(min.into(), max.into());
+
+                self.tree
+                    .locate_in_envelope(&aabb)
+                    .map(|e| (e.vec(), e.entity()))
+                    .collect()
+            }
+        }
+
+        impl<Comp: TComp> UpdateSpatialAccess for $treename<Comp> {
+            /// Only changed points are re-inserted and only removed entities are removed.
+            ///
+            /// If the tree is empty, all points are bulk loaded instead, which is a lot faster than inserting them one by one.
+            ///
+            /// Several points of the same entity have to be next to each other, they replace its previous points together.
+            fn update(
+                &mut self,
+                data: impl Iterator<Item = (Self::Point, bool)>,
+                removed: impl Iterator<Item = Entity>,
+            ) -> bool {
+                let mut updated = false;
+                for entity in removed {
+                    updated |= self.remove_entity(entity);
+                }
+
+                if self.tree.size() == 0 {
+                    let points: Vec<$pt> = data.map(|(p, _)| p).collect();
+                    updated |= !points.is_empty();
+                    self.entities.clear();
+                    for p in &points {
+                        if let Some(entity) = p.entity {
+                            self.entities.entry(entity).or_default().push(*p);
+                        }
+                    }
+                    self.tree = BaseRTree::bulk_load(points);
+                    return updated;
+                }
+
+                // consecutive points of the same entity are its samples, which replace its previous points together
+                let mut last = None;
+                for (p, changed) in data {
+                    if changed {
+                        if p.entity.is_some() && p.entity == last {
+                            self.add_sample(p);
+                        } else {
+                            self.add(p);
+                        }
+                        last = p.entity;
+                        updated = true;
+                    }
+                }
+                updated
+            }
+
+            /// Adds the point, replacing the previous points of the same [`Entity`] if there were any.
+            fn add(&mut self, point: Self::Point) {
+                if let Some(old) = point
+                    .entity
+                    .and_then(|e| self.entities.insert(e, smallvec![point]))
+                {
+                    if old.as_slice() == [point] {
+                        return;
+                    }
+                    for old in &old {
+                        self.tree.remove(old);
+                    }
+                }
+                self.tree.insert(point);
+            }
+
+            fn remove_point(&mut self, point: Self::Point) -> bool {
+                if let Some(entity) = point.entity {
+                    if let Some(points) = self.entities.get_mut(&entity) {
+                        if let Some(idx) = points.iter().position(|p| *p == point) {
+                            points.swap_remove(idx);
+                        }
+                        if points.is_empty() {
+                            self.entities.remove(&entity);
+                        }
+                    }
+                }
+                self.tree.remove(&point).is_some()
+            }
+
+            fn remove_entity(&mut self, entity: Entity) -> bool {
+                self.entities.remove(&entity).is_some_and(|points| {
+                    points.iter().fold(false, |removed, point| {
+                        self.tree.remove(point).is_some() | removed
+                    })
+                })
+            }
+
+            fn clear(&mut self) {
+                self.tree = BaseRTree::new();
+                self.entities.clear();
+            }
+        }
+    };
+}
+rtree_impl!(crate::point::Point2, RTree2);
+rtree_impl!(crate::point::Point3, RTree3);
+rtree_impl!(crate::point::Point3A, RTree3A);
+rtree_impl!(crate::point::PointD2, RTreeD2);
+rtree_impl!(crate::point::PointD3, RTreeD3);
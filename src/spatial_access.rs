@@ -1,8 +1,164 @@
-use bevy::prelude::*;
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::tasks::{ComputeTaskPool, ParallelSlice, TaskPool};
+use bevy::{ecs::entity::EntityHashSet, prelude::*};
+use num_traits::One;
 
-use crate::{point::SpatialPoint, TComp};
+use crate::{
+    metric::Metric,
+    point::{squared, SpatialPoint},
+    TComp,
+};
+use std::{cmp::Ordering, ops::ControlFlow};
+
+/// Sort query results by the scalar (distance, `t`, ...) they are paired with.
+pub(crate) fn sort_by_scalar<S: PartialOrd, T>(results: &mut [(S, T)]) {
+    results.sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+}
+
+/// Run `query` for every location in `locs`, returning the results in the same order.
+///
+/// Splits `locs` into chunks which are queried in parallel on the [`ComputeTaskPool`], or serially on wasm.
+pub(crate) fn batch<V: Sync, R: Send + 'static>(
+    locs: &[V],
+    query: impl Fn(&V) -> R + Send + Sync,
+) -> Vec<R> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+        locs.par_splat_map(pool, None, |_, chunk| {
+            chunk.iter().map(&query).collect::<Vec<_>>()
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        locs.iter().map(query).collect()
+    }
+}
+
+/// Keep only the first result of every entity, results without an entity are all kept.
+pub(crate) fn dedup_entities<S, V>(
+    results: Vec<(S, (V, Option<Entity>))>,
+) -> Vec<(S, (V, Option<Entity>))> {
+    let mut seen = EntityHashSet::default();
+    results
+        .into_iter()
+        .filter(|(_, (_, entity))| entity.is_none_or(|entity| seen.insert(entity)))
+        .collect()
+}
+
+/// The squared distance between two locations, as measured between the points of type `P`.
+pub(crate) fn distance_squared<P>(a: P::Vec, b: P::Vec) -> P::Scalar
+where
+    P: SpatialPoint + From<(Entity, P::Vec)>,
+{
+    let (a, b): (P, P) = (
+        (Entity::PLACEHOLDER, a).into(),
+        (Entity::PLACEHOLDER, b).into(),
+    );
+    a.distance_squared(&b)
+}
+
+/// The points found so far by a k-nearest search, sorted by distance, closest first.
+pub(crate) trait NearestFound<S, P> {
+    /// The number of points found so far.
+    fn count(&self) -> usize;
+    /// The distance of the point at `idx`.
+    fn distance_at(&self, idx: usize) -> S;
+    /// Insert `point` at `idx`, dropping the farthest point if there are `k` already.
+    fn insert_at(&mut self, k: usize, idx: usize, distance: S, point: P);
+}
+
+impl<S: Copy, P> NearestFound<S, P> for Vec<(S, P)> {
+    fn count(&self) -> usize {
+        self.len()
+    }
+
+    fn distance_at(&self, idx: usize) -> S {
+        self[idx].0
+    }
+
+    fn insert_at(&mut self, k: usize, idx: usize, distance: S, point: P) {
+        if self.len() == k {
+            self.pop();
+        }
+        self.insert(idx, (distance, point));
+    }
+}
+
+/// Collects the points found by a k-nearest search directly into a query result buffer, after the results already in it.
+///
+/// Instead of storing the distances, they are recomputed from the positions with `distance`,
+/// so the buffer of the caller can be reused without allocating a separate one for the search.
+pub(crate) struct NearestInto<'o, V, F> {
+    out: &'o mut Vec<(V, Option<Entity>)>,
+    start: usize,
+    distance: F,
+}
+
+impl<'o, V, F> NearestInto<'o, V, F> {
+    pub(crate) fn new(out: &'o mut Vec<(V, Option<Entity>)>, distance: F) -> Self {
+        let start = out.len();
+        Self {
+            out,
+            start,
+            distance,
+        }
+    }
+}
+
+impl<P, F> NearestFound<P::Scalar, &P> for NearestInto<'_, P::Vec, F>
+where
+    P: SpatialPoint,
+    F: Fn(P::Vec) -> P::Scalar,
+{
+    fn count(&self) -> usize {
+        self.out.len() - self.start
+    }
+
+    fn distance_at(&self, idx: usize) -> P::Scalar {
+        (self.distance)(self.out[self.start + idx].0)
+    }
+
+    fn insert_at(&mut self, k: usize, idx: usize, _: P::Scalar, point: &P) {
+        if self.out.len() - self.start == k {
+            self.out.pop();
+        }
+        self.out
+            .insert(self.start + idx, (point.vec(), point.entity()));
+    }
+}
+
+/// Insert `point` into `found`, which is sorted by distance and holds at most `k` points.
+pub(crate) fn insert_nearest<S: PartialOrd + Copy, P>(
+    found: &mut impl NearestFound<S, P>,
+    k: usize,
+    dist: S,
+    point: P,
+) {
+    let count = found.count();
+    if k == 0 || (count == k && found.distance_at(count - 1) <= dist) {
+        return;
+    }
+    // binary search for the first point further away than `dist`
+    let (mut lo, mut hi) = (0, count);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if found.distance_at(mid) <= dist {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    found.insert_at(k, lo, dist, point);
+}
 
 // todo: change Point to impl IntoPoint?
+/// Trait for updating point-based spatial datastructures.
+///
+/// Used by [`AutomaticUpdate`](crate::AutomaticUpdate), but can also be used to fill a datastructure manually.
 #[allow(clippy::module_name_repetitions)]
 pub trait UpdateSpatialAccess: SpatialAccess {
     /// Updates the underlying datastructure
@@ -10,20 +166,26 @@ pub trait UpdateSpatialAccess: SpatialAccess {
     /// The boolean indicates if the point needs to be updated or is a existing point.
     /// data should always include all points, even if they are not updated.
     /// This is for datastructures like ``KDTree``, which need to be fully rebuilt.
+    /// Several points of the same entity, like its [`SamplePoints`](crate::point::SamplePoints), are next to each other.
+    ///
+    /// Returns whether queries can see the update, [`AutomaticUpdate`](crate::AutomaticUpdate) only marks the resource as changed then.
     fn update(
         &mut self,
         data: impl Iterator<Item = (Self::Point, bool)>,
         removed: impl Iterator<Item = Entity>,
-    ) {
+    ) -> bool {
+        let mut updated = false;
         for (p, changed) in data {
             if changed {
                 self.remove_point(p);
                 self.add(p);
+                updated = true;
             }
         }
         for e in removed {
-            self.remove_entity(e);
+            updated |= self.remove_entity(e);
         }
+        updated
     }
     /// Adds the point to the underlying datastructure.
     fn add(&mut self, point: Self::Point);
@@ -36,6 +198,10 @@ pub trait UpdateSpatialAccess: SpatialAccess {
 }
 
 /// Trait for accessing point-based spatial datastructures.
+///
+/// Only [`nearest_neighbour`](Self::nearest_neighbour), [`k_nearest_neighbour`](Self::k_nearest_neighbour)
+/// and [`within_distance`](Self::within_distance) have to be implemented, all other queries are built on top of them.
+/// The datastructures in this crate override most of them with faster versions.
 pub trait SpatialAccess: Send + Sync + 'static {
     /// The point type, can be anything implementing [`SpatialPoint`].
     type Point: SpatialPoint;
@@ -46,6 +212,7 @@ pub trait SpatialAccess: Send + Sync + 'static {
 
     /// Get the nearest neighbour to `loc`.
     /// Be aware that that distance to the returned point will be zero if `loc` is part of the datastructure.
+    /// Use [`SpatialAccess::nearest_neighbour_filtered`] to skip it.
     fn nearest_neighbour(&self, loc: <Self::Point as SpatialPoint>::Vec) -> Option<Self::ResultT>;
 
     /// Return the k nearest neighbours to `loc`.
@@ -55,12 +222,558 @@ pub trait SpatialAccess: Send + Sync + 'static {
         k: usize,
     ) -> Vec<Self::ResultT>;
 
+    /// Get the nearest neighbour to `loc` whose entity passes `filter`.
+    ///
+    /// Points without an entity are skipped.
+    /// Useful to skip the entity at `loc` itself, or to only look for entities in a certain state:
+    /// `tree.nearest_neighbour_filtered(loc, |e| e != me && !dead.contains(e))`.
+    fn nearest_neighbour_filtered(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+        filter: impl FnMut(Entity) -> bool,
+    ) -> Option<Self::ResultT>
+    where
+        Self::ResultT: Copy + Into<(<Self::Point as SpatialPoint>::Vec, Option<Entity>)>,
+    {
+        self.k_nearest_neighbour_filtered(loc, 1, filter)
+            .into_iter()
+            .next()
+    }
+
+    /// Return the k nearest neighbours to `loc` whose entities pass `filter`.
+    ///
+    /// Points without an entity are skipped. Results are sorted by distance, closest first.
+    fn k_nearest_neighbour_filtered(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+        k: usize,
+        mut filter: impl FnMut(Entity) -> bool,
+    ) -> Vec<Self::ResultT>
+    where
+        Self::ResultT: Copy + Into<(<Self::Point as SpatialPoint>::Vec, Option<Entity>)>,
+    {
+        // query more points until enough of them pass, or no points are left
+        let mut found = vec![];
+        let (mut checked, mut n) = (0, k);
+        while found.len() < k {
+            let nearest = self.k_nearest_neighbour(loc, n);
+            let exhausted = nearest.len() < n;
+            found.extend(
+                nearest
+                    .into_iter()
+                    .skip(checked)
+                    .filter(|result| (*result).into().1.is_some_and(&mut filter)),
+            );
+            if exhausted {
+                break;
+            }
+            checked = n;
+            n = n.saturating_mul(2);
+        }
+        found.truncate(k);
+        found
+    }
+
+    /// Return up to k nearest neighbours to `loc` which are within `max_distance` of it.
+    ///
+    /// Results are sorted by distance, closest first.
+    fn k_nearest_within(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+        k: usize,
+        max_distance: <Self::Point as SpatialPoint>::Scalar,
+    ) -> Vec<Self::ResultT>
+    where
+        Self::ResultT: Copy + Into<(<Self::Point as SpatialPoint>::Vec, Option<Entity>)>,
+        Self::Point: From<(Entity, <Self::Point as SpatialPoint>::Vec)>,
+    {
+        let max_distance = squared(max_distance);
+        self.k_nearest_neighbour_with_distance(loc, k)
+            .into_iter()
+            .take_while(|(distance, _)| *distance <= max_distance)
+            .map(|(_, result)| result)
+            .collect()
+    }
+
     /// Return all points which are within the specified distance.
     fn within_distance(
         &self,
         loc: <Self::Point as SpatialPoint>::Vec,
         distance: <Self::Point as SpatialPoint>::Scalar,
     ) -> Vec<Self::ResultT>;
+
+    /// Call `f` for every point which is within the specified distance, in no particular order.
+    ///
+    /// Doesn't allocate for the datastructures in this crate, return [`ControlFlow::Break`] from `f` to stop early:
+    /// ```
+    /// # use std::ops::ControlFlow;
+    /// # use bevy::prelude::*;
+    /// # use bevy_spatial::{kdtree::KDTree2, SpatialAccess};
+    /// # #[derive(Component)]
+    /// # struct Enemy;
+    /// # let tree = KDTree2::<Enemy>::default();
+    /// let mut any_in_range = false;
+    /// tree.for_each_within_distance(Vec2::ZERO, 10.0, |(_pos, _entity)| {
+    ///     any_in_range = true;
+    ///     ControlFlow::Break(())
+    /// });
+    /// ```
+    fn for_each_within_distance(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+        distance: <Self::Point as SpatialPoint>::Scalar,
+        f: impl FnMut(Self::ResultT) -> ControlFlow<()>,
+    ) {
+        let _ = self
+            .within_distance(loc, distance)
+            .into_iter()
+            .try_for_each(f);
+    }
+
+    /// Append all points which are within the specified distance to `out`, so its allocation can be reused.
+    ///
+    /// `out` is not cleared.
+    fn within_distance_into(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+        distance: <Self::Point as SpatialPoint>::Scalar,
+        out: &mut Vec<Self::ResultT>,
+    ) {
+        self.for_each_within_distance(loc, distance, |result| {
+            out.push(result);
+            ControlFlow::Continue(())
+        });
+    }
+
+    /// Append the k nearest neighbours to `loc` to `out`, so its allocation can be reused.
+    ///
+    /// `out` is not cleared. The appended results are sorted by distance, closest first.
+    /// The datastructures in this crate search directly into `out`, without allocating a buffer of their own.
+    fn k_nearest_neighbour_into(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+        k: usize,
+        out: &mut Vec<Self::ResultT>,
+    ) {
+        out.extend(self.k_nearest_neighbour(loc, k));
+    }
+
+    /// Call `f` for each of the k nearest neighbours to `loc`, closest first.
+    ///
+    /// Return [`ControlFlow::Break`] from `f` to stop early.
+    /// By default all k neighbours are collected first,
+    /// use [`SpatialAccess::k_nearest_neighbour_into`] to reuse that allocation instead.
+    fn for_each_k_nearest_neighbour(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+        k: usize,
+        f: impl FnMut(Self::ResultT) -> ControlFlow<()>,
+    ) {
+        let _ = self.k_nearest_neighbour(loc, k).into_iter().try_for_each(f);
+    }
+
+    /// Get the nearest neighbour to `loc`, together with its squared distance to `loc`.
+    fn nearest_neighbour_with_distance(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+    ) -> Option<(<Self::Point as SpatialPoint>::Scalar, Self::ResultT)>
+    where
+        Self::ResultT: Copy + Into<(<Self::Point as SpatialPoint>::Vec, Option<Entity>)>,
+        Self::Point: From<(Entity, <Self::Point as SpatialPoint>::Vec)>,
+    {
+        self.nearest_neighbour(loc).map(|result| {
+            (
+                distance_squared::<Self::Point>(loc, result.into().0),
+                result,
+            )
+        })
+    }
+
+    /// Return the k nearest neighbours to `loc`, each together with its squared distance to `loc`.
+    ///
+    /// Results are sorted by distance, closest first.
+    fn k_nearest_neighbour_with_distance(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+        k: usize,
+    ) -> Vec<(<Self::Point as SpatialPoint>::Scalar, Self::ResultT)>
+    where
+        Self::ResultT: Copy + Into<(<Self::Point as SpatialPoint>::Vec, Option<Entity>)>,
+        Self::Point: From<(Entity, <Self::Point as SpatialPoint>::Vec)>,
+    {
+        self.k_nearest_neighbour(loc, k)
+            .into_iter()
+            .map(|result| {
+                (
+                    distance_squared::<Self::Point>(loc, result.into().0),
+                    result,
+                )
+            })
+            .collect()
+    }
+
+    /// Return the k nearest neighbours to `loc` whose entities pass `filter`, each together with its squared distance to `loc`.
+    ///
+    /// Points without an entity are skipped. Results are sorted by distance, closest first.
+    fn k_nearest_neighbour_filtered_with_distance(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+        k: usize,
+        filter: impl FnMut(Entity) -> bool,
+    ) -> Vec<(<Self::Point as SpatialPoint>::Scalar, Self::ResultT)>
+    where
+        Self::ResultT: Copy + Into<(<Self::Point as SpatialPoint>::Vec, Option<Entity>)>,
+        Self::Point: From<(Entity, <Self::Point as SpatialPoint>::Vec)>,
+    {
+        self.k_nearest_neighbour_filtered(loc, k, filter)
+            .into_iter()
+            .map(|result| {
+                (
+                    distance_squared::<Self::Point>(loc, result.into().0),
+                    result,
+                )
+            })
+            .collect()
+    }
+
+    /// Return all points which are within the specified distance, each together with its squared distance to `loc`.
+    ///
+    /// Results are in no particular order, use [`SpatialAccess::within_distance_sorted`] to sort them by distance.
+    fn within_distance_with_distance(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+        distance: <Self::Point as SpatialPoint>::Scalar,
+    ) -> Vec<(<Self::Point as SpatialPoint>::Scalar, Self::ResultT)>
+    where
+        Self::ResultT: Copy + Into<(<Self::Point as SpatialPoint>::Vec, Option<Entity>)>,
+        Self::Point: From<(Entity, <Self::Point as SpatialPoint>::Vec)>,
+    {
+        let mut results = vec![];
+        self.for_each_within_distance(loc, distance, |result| {
+            results.push((
+                distance_squared::<Self::Point>(loc, result.into().0),
+                result,
+            ));
+            ControlFlow::Continue(())
+        });
+        results
+    }
+
+    /// Return all points which are within the specified distance, each together with its squared distance to `loc`.
+    ///
+    /// Results are sorted by distance, closest first.
+    fn within_distance_sorted(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+        distance: <Self::Point as SpatialPoint>::Scalar,
+    ) -> Vec<(<Self::Point as SpatialPoint>::Scalar, Self::ResultT)>
+    where
+        Self::ResultT: Copy + Into<(<Self::Point as SpatialPoint>::Vec, Option<Entity>)>,
+        Self::Point: From<(Entity, <Self::Point as SpatialPoint>::Vec)>,
+    {
+        let mut results = self.within_distance_with_distance(loc, distance);
+        sort_by_scalar(&mut results);
+        results
+    }
+
+    /// Return the `k` nearest entities to `loc`, each with its closest point and the squared distance to it.
+    ///
+    /// Unlike [`SpatialAccess::k_nearest_neighbour_with_distance`], entities with several points,
+    /// like their [`SamplePoints`](crate::point::SamplePoints), are only returned once.
+    /// Results are sorted by distance, closest first.
+    fn k_nearest_entities(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+        k: usize,
+    ) -> Vec<(<Self::Point as SpatialPoint>::Scalar, Self::ResultT)>
+    where
+        Self: SpatialAccess<
+            ResultT = (
+                <<Self as SpatialAccess>::Point as SpatialPoint>::Vec,
+                Option<Entity>,
+            ),
+        >,
+        Self::Point: From<(
+            Entity,
+            <<Self as SpatialAccess>::Point as SpatialPoint>::Vec,
+        )>,
+    {
+        // query more points until there are enough entities, or no points are left
+        let mut n = k;
+        loop {
+            let found = self.k_nearest_neighbour_with_distance(loc, n);
+            let exhausted = found.len() < n;
+            let mut entities = dedup_entities(found);
+            if entities.len() >= k || exhausted {
+                entities.truncate(k);
+                return entities;
+            }
+            n = n.saturating_mul(2);
+        }
+    }
+
+    /// Return all entities within `distance` of `loc`, each with its closest point and the squared distance to it.
+    ///
+    /// Unlike [`SpatialAccess::within_distance_sorted`], entities with several points,
+    /// like their [`SamplePoints`](crate::point::SamplePoints), are only returned once.
+    /// Results are sorted by distance, closest first.
+    fn within_distance_entities(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+        distance: <Self::Point as SpatialPoint>::Scalar,
+    ) -> Vec<(<Self::Point as SpatialPoint>::Scalar, Self::ResultT)>
+    where
+        Self: SpatialAccess<
+            ResultT = (
+                <<Self as SpatialAccess>::Point as SpatialPoint>::Vec,
+                Option<Entity>,
+            ),
+        >,
+        Self::Point: From<(
+            Entity,
+            <<Self as SpatialAccess>::Point as SpatialPoint>::Vec,
+        )>,
+    {
+        dedup_entities(self.within_distance_sorted(loc, distance))
+    }
+
+    /// Get the nearest neighbour to every location in `locs`, in parallel.
+    ///
+    /// The result at each index belongs to the location at the same index.
+    /// Runs on the [`ComputeTaskPool`](bevy::tasks::ComputeTaskPool), or serially on wasm.
+    fn nearest_neighbour_batch(
+        &self,
+        locs: &[<Self::Point as SpatialPoint>::Vec],
+    ) -> Vec<Option<Self::ResultT>>
+    where
+        Self::ResultT: Send + 'static,
+    {
+        batch(locs, |loc| self.nearest_neighbour(*loc))
+    }
+
+    /// Get the k nearest neighbours to every location in `locs`, in parallel.
+    ///
+    /// The results at each index belong to the location at the same index.
+    /// Runs on the [`ComputeTaskPool`](bevy::tasks::ComputeTaskPool), or serially on wasm.
+    fn k_nearest_neighbour_batch(
+        &self,
+        locs: &[<Self::Point as SpatialPoint>::Vec],
+        k: usize,
+    ) -> Vec<Vec<Self::ResultT>>
+    where
+        Self::ResultT: Send + 'static,
+    {
+        batch(locs, |loc| self.k_nearest_neighbour(*loc, k))
+    }
+
+    /// Get all points within the specified distance of every location in `locs`, in parallel.
+    ///
+    /// The results at each index belong to the location at the same index.
+    /// Runs on the [`ComputeTaskPool`](bevy::tasks::ComputeTaskPool), or serially on wasm.
+    fn within_distance_batch(
+        &self,
+        locs: &[<Self::Point as SpatialPoint>::Vec],
+        distance: <Self::Point as SpatialPoint>::Scalar,
+    ) -> Vec<Vec<Self::ResultT>>
+    where
+        Self::ResultT: Send + 'static,
+        <Self::Point as SpatialPoint>::Scalar: Sync,
+    {
+        batch(locs, |loc| self.within_distance(*loc, distance))
+    }
+}
+
+/// Trait for finding pairs of nearby points, within one datastructure or between two of them.
+///
+/// Split from [`SpatialAccess`] as it needs access to every stored point.
+#[allow(clippy::module_name_repetitions)]
+pub trait SpatialPairAccess: SpatialAccess {
+    /// Iterate over all points stored in the datastructure, in no particular order.
+    fn iter_points(&self) -> impl Iterator<Item = &Self::Point>;
+
+    /// Call `f` once for every pair of points which are within the specified distance of each other.
+    ///
+    /// Each pair is reported once, in no particular order, and a point is never paired with itself.
+    /// Useful as a broadphase, for example to find all units within attack range of each other.
+    ///
+    /// The default implementation compares every point with every other one,
+    /// the tree and grid based datastructures only compare nearby points.
+    fn for_each_pair_within_distance(
+        &self,
+        distance: <Self::Point as SpatialPoint>::Scalar,
+        mut f: impl FnMut(&Self::Point, &Self::Point),
+    ) {
+        let distance_squared = squared(distance);
+        for (i, a) in self.iter_points().enumerate() {
+            for b in self.iter_points().skip(i + 1) {
+                if a.distance_squared(b) <= distance_squared {
+                    f(a, b);
+                }
+            }
+        }
+    }
+
+    /// Return every pair of points which are within the specified distance of each other.
+    ///
+    /// See [`SpatialPairAccess::for_each_pair_within_distance`].
+    fn pairs_within_distance(
+        &self,
+        distance: <Self::Point as SpatialPoint>::Scalar,
+    ) -> Vec<(Self::Point, Self::Point)> {
+        let mut pairs = vec![];
+        self.for_each_pair_within_distance(distance, |a, b| pairs.push((*a, *b)));
+        pairs
+    }
+
+    /// Return every pair of points which are within the specified distance of each other, searching in parallel.
+    ///
+    /// Runs on the [`ComputeTaskPool`](bevy::tasks::ComputeTaskPool) for the KD-trees, or serially on wasm.
+    /// The default implementation is the same as [`SpatialPairAccess::pairs_within_distance`].
+    fn pairs_within_distance_par(
+        &self,
+        distance: <Self::Point as SpatialPoint>::Scalar,
+    ) -> Vec<(Self::Point, Self::Point)> {
+        self.pairs_within_distance(distance)
+    }
+
+    /// Call `f` for every pair of a point in this datastructure and a point in `other` which are within the specified distance.
+    ///
+    /// `other` can be a different datastructure with the same point type, tracking a different component.
+    /// For example, every enemy within range of a turret: `turrets.join_within_distance(&enemies, range, |turret, enemy| ...)`.
+    /// Searches `other` around each point of this datastructure,
+    /// see [`KDTree2::dual_join_within_distance`](crate::kdtree::KDTree2::dual_join_within_distance) for a faster join between two KD-trees.
+    fn join_within_distance<Other>(
+        &self,
+        other: &Other,
+        distance: <Self::Point as SpatialPoint>::Scalar,
+        mut f: impl FnMut(&Self::Point, Other::ResultT),
+    ) where
+        Other: SpatialAccess<Point = Self::Point>,
+    {
+        for a in self.iter_points() {
+            other.for_each_within_distance(a.vec(), distance, |b| {
+                f(a, b);
+                ControlFlow::Continue(())
+            });
+        }
+    }
+
+    /// Get the nearest neighbour in `other` for every point in this datastructure, in parallel.
+    ///
+    /// `other` can be a different datastructure with the same point type, tracking a different component.
+    /// Runs on the [`ComputeTaskPool`](bevy::tasks::ComputeTaskPool), or serially on wasm.
+    fn join_nearest<Other>(&self, other: &Other) -> Vec<(Self::Point, Option<Other::ResultT>)>
+    where
+        Other: SpatialAccess<Point = Self::Point>,
+        Other::ResultT: Send + 'static,
+        Self::Point: Send + Sync + 'static,
+    {
+        let points: Vec<Self::Point> = self.iter_points().copied().collect();
+        batch(&points, |a| (*a, other.nearest_neighbour(a.vec())))
+    }
 }
 
-// TODO: SpatialAABBAccess trait definition - should it be separate from SpatialAccess or depend on it?
+/// Trait for querying spatial datastructures with a different distance [`Metric`] than the euclidean distance.
+///
+/// Distances passed in are radii like everywhere else, distances returned are measured by the metric,
+/// for example squared for [`Euclidean`](crate::metric::Euclidean).
+/// Only the [KD-trees](crate::kdtree) implement it, the other datastructures only measure euclidean distances.
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_spatial::{kdtree::KDTree2, metric::Manhattan, SpatialMetricAccess};
+/// # #[derive(Component)]
+/// # struct Unit;
+/// fn in_move_range(tree: Res<KDTree2<Unit>>) {
+///     // every unit which can be reached in 3 steps on a grid without diagonal moves
+///     for (steps, (pos, entity)) in tree.within_distance_by(Vec2::ZERO, 3.0, &Manhattan) {
+///         // ...
+///     }
+/// }
+/// # bevy::ecs::system::assert_is_system(in_move_range);
+/// ```
+#[allow(clippy::module_name_repetitions)]
+pub trait SpatialMetricAccess: SpatialAccess {
+    /// Get the nearest neighbour to `loc` as measured by `metric`, together with its distance.
+    fn nearest_neighbour_by(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+        metric: &impl Metric<Self::Point>,
+    ) -> Option<(<Self::Point as SpatialPoint>::Scalar, Self::ResultT)> {
+        self.k_nearest_neighbour_by(loc, 1, metric)
+            .into_iter()
+            .next()
+    }
+
+    /// Get the `k` nearest neighbours to `loc` as measured by `metric`, together with their distance.
+    ///
+    /// Results are sorted by distance, closest first.
+    fn k_nearest_neighbour_by(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+        k: usize,
+        metric: &impl Metric<Self::Point>,
+    ) -> Vec<(<Self::Point as SpatialPoint>::Scalar, Self::ResultT)>;
+
+    /// Get all points within `distance` of `loc` as measured by `metric`, together with their distance.
+    ///
+    /// `distance` is converted with [`Metric::radius`] first. Results are in no particular order.
+    fn within_distance_by(
+        &self,
+        loc: <Self::Point as SpatialPoint>::Vec,
+        distance: <Self::Point as SpatialPoint>::Scalar,
+        metric: &impl Metric<Self::Point>,
+    ) -> Vec<(<Self::Point as SpatialPoint>::Scalar, Self::ResultT)>;
+}
+
+/// Trait for accessing point-based spatial datastructures by axis-aligned bounding box (AABB).
+///
+/// Useful for box selection, culling against a screen rectangle or region triggers.
+#[allow(clippy::module_name_repetitions)]
+pub trait SpatialAABBAccess: SpatialAccess {
+    /// Return all points which are inside the AABB spanned by `min` and `max`, including its boundary.
+    ///
+    /// `min` and `max` can be any two opposite corners of the AABB, the coordinates will be sorted.
+    fn within_aabb(
+        &self,
+        min: <Self::Point as SpatialPoint>::Vec,
+        max: <Self::Point as SpatialPoint>::Vec,
+    ) -> Vec<Self::ResultT>;
+}
+
+/// Trait for casting rays and line segments against point-based spatial datastructures.
+///
+/// Useful for hitscan weapons or mouse picking: find every point within a radius of the ray, sorted along it.
+/// Only implemented for the floating point datastructures, as the position along the ray is fractional.
+#[allow(clippy::module_name_repetitions)]
+pub trait SpatialRayAccess: SpatialAccess {
+    /// Return all points within `radius` of the ray starting at `origin`, going along `direction` up to `origin + direction * max_t`.
+    ///
+    /// Each result comes with the `t` of the point on the ray closest to it, hits are sorted by `t`.
+    /// `t` is in multiples of `direction`, so a normalized `direction` makes `t` the distance along the ray.
+    /// `max_t` has to be finite.
+    fn cast_ray(
+        &self,
+        origin: <Self::Point as SpatialPoint>::Vec,
+        direction: <Self::Point as SpatialPoint>::Vec,
+        max_t: <Self::Point as SpatialPoint>::Scalar,
+        radius: <Self::Point as SpatialPoint>::Scalar,
+    ) -> Vec<(<Self::Point as SpatialPoint>::Scalar, Self::ResultT)>;
+
+    /// Return all points within `radius` of the line segment from `start` to `end`.
+    ///
+    /// Each result comes with the `t` of the point on the segment closest to it, from 0 at `start` to 1 at `end`.
+    /// Hits are sorted by `t`.
+    fn cast_segment(
+        &self,
+        start: <Self::Point as SpatialPoint>::Vec,
+        end: <Self::Point as SpatialPoint>::Vec,
+        radius: <Self::Point as SpatialPoint>::Scalar,
+    ) -> Vec<(<Self::Point as SpatialPoint>::Scalar, Self::ResultT)> {
+        self.cast_ray(
+            start,
+            end - start,
+            <Self::Point as SpatialPoint>::Scalar::one(),
+            radius,
+        )
+    }
+}
@@ -0,0 +1,100 @@
+use bevy::{
+    ecs::{
+        query::{QueryData, QueryFilter, ROQueryItem},
+        system::SystemParam,
+    },
+    prelude::*,
+};
+
+use crate::{
+    automatic_systems::{GlamVec, Scalar},
+    SpatialAccess,
+};
+
+/// [`SystemParam`] combining a spatial datastructure with a [`Query`], to get query items instead of entities from spatial queries.
+///
+/// Results come with the squared distance to the queried location, as measured by the datastructure.
+/// On a [wrapped](crate::kdtree::KDTree2::with_wrap) KD-tree, that is the shorter distance across its edges.
+/// Entities which don't match the query, for example because they were despawned since the datastructure was updated, are skipped.
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_spatial::{kdtree::KDTree2, SpatialQuery};
+/// #[derive(Component)]
+/// struct Enemy;
+///
+/// #[derive(Component)]
+/// struct Health(f32);
+///
+/// fn nearest_enemy(enemies: SpatialQuery<KDTree2<Enemy>, (Entity, &Health)>) {
+///     if let Some((distance_squared, (entity, health))) = enemies.nearest(Vec2::ZERO) {
+///         // ...
+///     }
+/// }
+/// # bevy::ecs::system::assert_is_system(nearest_enemy);
+/// ```
+#[derive(SystemParam)]
+pub struct SpatialQuery<'w, 's, SpatialDS, D, F = ()>
+where
+    SpatialDS: SpatialAccess + Resource,
+    D: QueryData + 'static,
+    F: QueryFilter + 'static,
+{
+    /// The spatial datastructure.
+    pub tree: Res<'w, SpatialDS>,
+    /// The query results are looked up in.
+    pub query: Query<'w, 's, D, F>,
+}
+
+impl<SpatialDS, D, F> SpatialQuery<'_, '_, SpatialDS, D, F>
+where
+    SpatialDS: SpatialAccess<ResultT = (GlamVec<SpatialDS>, Option<Entity>)> + Resource,
+    SpatialDS::Point: From<(Entity, GlamVec<SpatialDS>)>,
+    D: QueryData + 'static,
+    F: QueryFilter + 'static,
+{
+    fn get(
+        &self,
+        (distance, (_, entity)): (Scalar<SpatialDS>, SpatialDS::ResultT),
+    ) -> Option<(Scalar<SpatialDS>, ROQueryItem<'_, D>)> {
+        Some((distance, self.query.get(entity?).ok()?))
+    }
+
+    /// Get the nearest entity to `loc` which matches the query.
+    pub fn nearest(
+        &self,
+        loc: GlamVec<SpatialDS>,
+    ) -> Option<(Scalar<SpatialDS>, ROQueryItem<'_, D>)> {
+        let result = self
+            .tree
+            .k_nearest_neighbour_filtered_with_distance(loc, 1, |e| self.query.contains(e))
+            .into_iter()
+            .next()?;
+        self.get(result)
+    }
+
+    /// Get the `k` nearest entities to `loc` which match the query, sorted by distance.
+    pub fn k_nearest(
+        &self,
+        loc: GlamVec<SpatialDS>,
+        k: usize,
+    ) -> Vec<(Scalar<SpatialDS>, ROQueryItem<'_, D>)> {
+        self.tree
+            .k_nearest_neighbour_filtered_with_distance(loc, k, |e| self.query.contains(e))
+            .into_iter()
+            .filter_map(|result| self.get(result))
+            .collect()
+    }
+
+    /// Get all entities within `distance` of `loc` which match the query, in no particular order.
+    pub fn within_distance(
+        &self,
+        loc: GlamVec<SpatialDS>,
+        distance: Scalar<SpatialDS>,
+    ) -> Vec<(Scalar<SpatialDS>, ROQueryItem<'_, D>)> {
+        self.tree
+            .within_distance_with_distance(loc, distance)
+            .into_iter()
+            .filter_map(|result| self.get(result))
+            .collect()
+    }
+}
@@ -0,0 +1,46 @@
+//! Checks that KD-trees built in the background are swapped in.
+
+mod common;
+
+use std::{thread, time::Duration};
+
+use bevy::prelude::*;
+use bevy_spatial::{kdtree::KDTree3, AutomaticUpdate, SpatialAccess, SpatialStructure};
+use common::app;
+
+#[derive(Component)]
+struct Marker;
+
+/// Runs frames until the nearest entity to the origin is at `expected`, returns whether it was within a few seconds.
+fn wait_for(app: &mut App, expected: Vec3) -> bool {
+    for _ in 0..500 {
+        app.update();
+        let tree = app.world().resource::<KDTree3<Marker>>();
+        if tree.nearest_neighbour(Vec3::ZERO).map(|(v, _)| v) == Some(expected) {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+#[test]
+fn background_builds_are_swapped_in() {
+    let mut app = app(AutomaticUpdate::<Marker>::new()
+        .with_spatial_ds(SpatialStructure::KDTree3)
+        .with_async_rebuild(true));
+    let entity = app
+        .world_mut()
+        .spawn((Marker, Transform::from_xyz(1.0, 2.0, 3.0)))
+        .id();
+    app.world_mut()
+        .spawn((Marker, Transform::from_xyz(50.0, 0.0, 0.0)));
+    assert!(wait_for(&mut app, Vec3::new(1.0, 2.0, 3.0)));
+
+    // moving an entity starts another build, which replaces the first tree
+    app.world_mut()
+        .get_mut::<Transform>(entity)
+        .unwrap()
+        .translation = Vec3::new(70.0, 0.0, 0.0);
+    assert!(wait_for(&mut app, Vec3::new(50.0, 0.0, 0.0)));
+}
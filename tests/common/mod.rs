@@ -0,0 +1,85 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_spatial::{kdtree::KDTree2, point::Point2, UpdateSpatialAccess};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Size of the world the points are placed in, along both axes.
+pub const SIZE: f32 = 100.0;
+
+/// `count` random points inside the world, with the entities `first..first + count`.
+pub fn random_points(seed: u64, count: u32, first: u32) -> Vec<Point2> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count)
+        .map(|i| {
+            let vec = Vec2::new(rng.gen_range(0.0..SIZE), rng.gen_range(0.0..SIZE));
+            (vec, Entity::from_raw(first + i)).into()
+        })
+        .collect()
+}
+
+/// A KD-tree built from `points`.
+pub fn kdtree<Comp: Component>(points: &[Point2]) -> KDTree2<Comp> {
+    let mut tree = KDTree2::default();
+    tree.update(points.iter().map(|p| (*p, true)), std::iter::empty());
+    tree
+}
+
+pub fn sorted<T: Ord>(mut items: Vec<T>) -> Vec<T> {
+    items.sort_unstable();
+    items
+}
+
+/// Sorted entities of the results.
+pub fn entities<V>(results: impl IntoIterator<Item = (V, Option<Entity>)>) -> Vec<Entity> {
+    sorted(results.into_iter().filter_map(|(_, e)| e).collect())
+}
+
+/// Sorted entity pairs, with the smaller entity first in each pair.
+pub fn pairs(pairs: impl IntoIterator<Item = (Entity, Entity)>) -> Vec<(Entity, Entity)> {
+    sorted(
+        pairs
+            .into_iter()
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect(),
+    )
+}
+
+/// The entity of the point closest to `loc` by brute force.
+pub fn nearest(points: &[Point2], loc: Vec2) -> Option<Entity> {
+    points
+        .iter()
+        .min_by(|a, b| {
+            a.vec
+                .distance_squared(loc)
+                .total_cmp(&b.vec.distance_squared(loc))
+        })
+        .and_then(|p| p.entity)
+}
+
+/// The sorted entities of the points within `radius` of `loc` by brute force.
+pub fn within(points: &[Point2], loc: Vec2, radius: f32) -> Vec<Entity> {
+    sorted(
+        points
+            .iter()
+            .filter(|p| p.vec.distance_squared(loc) <= radius * radius)
+            .filter_map(|p| p.entity)
+            .collect(),
+    )
+}
+
+/// An app running `plugin`, whose frames advance the time by more than the default delay between updates,
+/// so the datastructure is updated on every frame.
+pub fn app(plugin: impl Plugin) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, plugin))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )));
+    // the first frame doesn't advance the time yet
+    app.update();
+    app
+}
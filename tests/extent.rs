@@ -0,0 +1,175 @@
+//! Compares the extent trees against a linear scan over the same bounds.
+
+mod common;
+
+use std::time::Duration;
+
+use bevy::{math::Affine3A, prelude::*};
+use bevy_spatial::{
+    extent::{Bounds, Extent, ExtentTree3, SpatialExtent, SpatialExtentAccess, UpdateExtentAccess},
+    AutomaticExtentUpdate, TimestepLength,
+};
+use common::{app, sorted};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(Component)]
+struct Marker;
+
+fn random_bounds(seed: u64, count: u32) -> Vec<Bounds<Vec3>> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut vec = |range: std::ops::Range<f32>| {
+        Vec3::new(
+            rng.gen_range(range.clone()),
+            rng.gen_range(range.clone()),
+            rng.gen_range(range),
+        )
+    };
+    (0..count)
+        .map(|i| {
+            let size = vec(0.1..6.0);
+            let extent = if i % 2 == 0 {
+                Extent::sphere(size.x)
+            } else {
+                Extent::cuboid(size)
+            };
+            let rotation = Quat::from_euler(EulerRot::XYZ, size.x, size.y, size.z);
+            let affine = Affine3A::from_rotation_translation(rotation, vec(0.0..100.0));
+            Bounds::from_extent(Entity::from_raw(i), &extent, &affine)
+        })
+        .collect()
+}
+
+fn filled(bounds: &[Bounds<Vec3>]) -> ExtentTree3<Marker> {
+    let mut tree = ExtentTree3::default();
+    assert!(tree.update(bounds.iter().map(|b| (*b, true))));
+    tree
+}
+
+fn entities<'a>(bounds: impl IntoIterator<Item = &'a Bounds<Vec3>>) -> Vec<Entity> {
+    sorted(bounds.into_iter().filter_map(|b| b.entity).collect())
+}
+
+fn queries() -> Vec<Vec3> {
+    let mut rng = StdRng::seed_from_u64(99);
+    (0..30)
+        .map(|_| {
+            Vec3::new(
+                rng.gen_range(-10.0..110.0),
+                rng.gen_range(-10.0..110.0),
+                50.0,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn overlap_queries_match_linear_scan() {
+    let bounds = random_bounds(1, 400);
+    let tree = filled(&bounds);
+
+    for loc in queries() {
+        for radius in [0.0, 2.5, 15.0] {
+            let expected = bounds
+                .iter()
+                .filter(|b| b.distance_squared(loc) <= radius * radius);
+            assert_eq!(
+                entities(&tree.within_distance(loc, radius)),
+                entities(expected)
+            );
+        }
+        let expected = bounds.iter().filter(|b| b.distance_squared(loc) == 0.0);
+        assert_eq!(entities(&tree.containing(loc)), entities(expected));
+
+        let (min, max) = (
+            loc - Vec3::new(8.0, 3.0, 20.0),
+            loc + Vec3::new(2.0, 12.0, 5.0),
+        );
+        let expected = bounds.iter().filter(|b| b.overlaps_aabb(min, max));
+        // the corners are sorted by the query
+        assert_eq!(entities(&tree.within_aabb(max, min)), entities(expected));
+
+        let mut distances: Vec<_> = bounds.iter().map(|b| b.distance_squared(loc)).collect();
+        distances.sort_by(f32::total_cmp);
+        let found: Vec<_> = tree
+            .k_nearest_neighbour(loc, 12)
+            .into_iter()
+            .map(|(d, _)| d)
+            .collect();
+        assert_eq!(found, distances[..12]);
+    }
+}
+
+#[test]
+fn removed_entities_are_skipped_until_rebuild() {
+    let bounds = random_bounds(2, 200);
+    let mut tree = filled(&bounds);
+    let removed: Vec<_> = (0..200).step_by(3).map(Entity::from_raw).collect();
+    for entity in &removed {
+        assert!(tree.remove_entity(*entity));
+    }
+    let live: Vec<_> = bounds
+        .iter()
+        .filter(|b| !removed.contains(&b.entity.unwrap()))
+        .copied()
+        .collect();
+
+    let check = |tree: &ExtentTree3<Marker>| {
+        assert_eq!(entities(tree.iter_bounds()), entities(&live));
+        for loc in queries() {
+            let expected = live.iter().filter(|b| b.distance_squared(loc) <= 100.0);
+            assert_eq!(
+                entities(&tree.within_distance(loc, 10.0)),
+                entities(expected)
+            );
+            let nearest = tree.nearest_neighbour(loc).unwrap().1;
+            assert!(!removed.contains(&nearest.entity.unwrap()));
+        }
+    };
+    check(&tree);
+
+    // the entities are gone from the update, which rebuilds the tree without them
+    assert!(tree.update(live.iter().map(|b| (*b, false))));
+    check(&tree);
+    assert!(!tree.update(live.iter().map(|b| (*b, false))));
+}
+
+#[derive(Component)]
+struct Radius(f32);
+
+impl SpatialExtent for Radius {
+    fn extent(&self) -> Extent {
+        Extent::sphere(self.0)
+    }
+}
+
+#[test]
+fn despawned_entities_are_removed_before_the_next_update() {
+    let mut app = app(AutomaticExtentUpdate::<Marker, Radius>::new());
+    let a = app
+        .world_mut()
+        .spawn((Marker, Radius(2.0), Transform::from_xyz(0.0, 0.0, 0.0)))
+        .id();
+    let b = app
+        .world_mut()
+        .spawn((Marker, Radius(1.0), Transform::from_xyz(10.0, 0.0, 0.0)))
+        .id();
+    app.update();
+    let tree = app.world().resource::<ExtentTree3<Marker>>();
+    assert_eq!(entities(tree.iter_bounds()), vec![a, b]);
+    assert_eq!(
+        entities(&tree.within_distance(Vec3::new(4.0, 0.0, 0.0), 2.0)),
+        vec![a]
+    );
+
+    app.world_mut().despawn(a);
+    // queries skip the despawned entity right away, without waiting for the next update tick
+    app.world_mut()
+        .resource_mut::<TimestepLength<Marker>>()
+        .set_duration(Duration::from_secs(60));
+    app.update();
+    let tree = app.world().resource::<ExtentTree3<Marker>>();
+    assert_eq!(entities(tree.iter_bounds()), vec![b]);
+    assert!(tree
+        .within_distance(Vec3::new(4.0, 0.0, 0.0), 2.0)
+        .is_empty());
+}
@@ -0,0 +1,76 @@
+//! Integer points of the KD-trees, checked against brute force.
+
+mod common;
+
+use bevy::{math::IVec2, prelude::*};
+use bevy_spatial::{
+    kdtree::KDTreeI2, point::IPoint2, SpatialAABBAccess, SpatialAccess, UpdateSpatialAccess,
+};
+use common::sorted;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(Component)]
+struct Marker;
+
+#[test]
+fn integer_tree_matches_brute_force() {
+    let mut rng = StdRng::seed_from_u64(12);
+    let points: Vec<IPoint2> = (0..300)
+        .map(|i| {
+            let vec = IVec2::new(rng.gen_range(-500..500), rng.gen_range(-500..500));
+            (vec, Entity::from_raw(i)).into()
+        })
+        .collect();
+    let mut tree = KDTreeI2::<Marker>::default();
+    tree.update(points.iter().map(|p| (*p, true)), std::iter::empty());
+
+    for loc in [IVec2::ZERO, IVec2::new(499, -500), points[3].vec] {
+        let distances = sorted(
+            points
+                .iter()
+                .map(|p| (p.vec - loc).length_squared())
+                .collect(),
+        );
+        let found: Vec<_> = tree
+            .k_nearest_neighbour_with_distance(loc, 15)
+            .into_iter()
+            .map(|(d, _)| d)
+            .collect();
+        assert_eq!(found, distances[..15]);
+
+        let within = points
+            .iter()
+            .filter(|p| (p.vec - loc).length_squared() <= 60 * 60)
+            .filter_map(|p| p.entity)
+            .collect();
+        let found = sorted(
+            tree.within_distance(loc, 60)
+                .into_iter()
+                .filter_map(|(_, e)| e)
+                .collect(),
+        );
+        assert_eq!(found, sorted(within));
+    }
+}
+
+#[test]
+fn integer_distances_saturate() {
+    let far = 1 << 30;
+    let points: Vec<IPoint2> = [IVec2::splat(-far), IVec2::splat(far), IVec2::new(far, -far)]
+        .into_iter()
+        .zip(0..)
+        .map(|(vec, i)| (vec, Entity::from_raw(i)).into())
+        .collect();
+    let mut tree = KDTreeI2::<Marker>::default();
+    tree.update(points.iter().map(|p| (*p, true)), std::iter::empty());
+
+    let (distance, _) = tree.nearest_neighbour_with_distance(IVec2::ZERO).unwrap();
+    assert_eq!(distance, i32::MAX);
+    assert_eq!(tree.k_nearest_neighbour(IVec2::ZERO, 3).len(), 3);
+    assert_eq!(tree.within_distance(IVec2::ZERO, i32::MAX).len(), 3);
+    assert_eq!(
+        tree.within_aabb(IVec2::splat(-far), IVec2::splat(far))
+            .len(),
+        3
+    );
+}
@@ -0,0 +1,43 @@
+//! Compares the joins between two KD-trees against brute force.
+
+mod common;
+
+use bevy::prelude::*;
+use bevy_spatial::{point::SpatialPoint, SpatialPairAccess};
+use common::{kdtree, random_points, sorted};
+
+#[derive(Component)]
+struct Marker;
+
+#[derive(Component)]
+struct Other;
+
+#[test]
+fn dual_join_matches_join_within_distance() {
+    let points = random_points(8, 300, 0);
+    let others = random_points(9, 150, 1000);
+    let tree = kdtree::<Marker>(&points);
+    let other_tree = kdtree::<Other>(&others);
+
+    for radius in [0.0, 3.0, 11.0] {
+        let mut expected = vec![];
+        for a in &points {
+            for b in &others {
+                if a.distance_squared(b) <= radius * radius {
+                    expected.push((a.entity.unwrap(), b.entity.unwrap()));
+                }
+            }
+        }
+        let mut dual = vec![];
+        tree.dual_join_within_distance(&other_tree, radius, |a, b| {
+            dual.push((a.entity.unwrap(), b.entity.unwrap()));
+        });
+        let mut join = vec![];
+        tree.join_within_distance(&other_tree, radius, |a, b| {
+            join.push((a.entity.unwrap(), b.1.unwrap()));
+        });
+        let expected = sorted(expected);
+        assert_eq!(sorted(dual), expected);
+        assert_eq!(sorted(join), expected);
+    }
+}
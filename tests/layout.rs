@@ -0,0 +1,58 @@
+//! Pins the layout of the trees built by `kd_tree`, which the KD-tree queries walk directly.
+//!
+//! If an upgrade of `kd_tree` changes how it sorts the points, these fail instead of the queries silently missing points.
+
+mod common;
+
+use bevy::prelude::*;
+use bevy_spatial::{
+    kdtree::{KDTree2, KDTree3},
+    point::Point3,
+    UpdateSpatialAccess,
+};
+use common::random_points;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(Component)]
+struct Marker;
+
+/// Checks that the median along `axis` is at `len / 2` of `items`,
+/// and that both halves are laid out the same way along the next axis.
+fn assert_layout<T>(items: &[T], axis: usize, dim: usize, at: &impl Fn(&T, usize) -> f32) {
+    if items.len() < 2 {
+        return;
+    }
+    let mid = items.len() / 2;
+    let median = at(&items[mid], axis);
+    assert!(items[..mid].iter().all(|item| at(item, axis) <= median));
+    assert!(items[mid + 1..].iter().all(|item| at(item, axis) >= median));
+    let next = (axis + 1) % dim;
+    assert_layout(&items[..mid], next, dim, at);
+    assert_layout(&items[mid + 1..], next, dim, at);
+}
+
+#[test]
+fn kdtree2_is_split_at_the_median_with_cycling_axes() {
+    for count in [1, 2, 3, 100, 257] {
+        let points = random_points(20, count, 0);
+        let mut tree = KDTree2::<Marker>::default();
+        tree.update(points.iter().map(|p| (*p, true)), std::iter::empty());
+        assert_eq!(tree.tree.len(), points.len());
+        assert_layout(&tree.tree, 0, 2, &|p, axis| p.vec[axis]);
+    }
+}
+
+#[test]
+fn kdtree3_is_split_at_the_median_with_cycling_axes() {
+    let mut rng = StdRng::seed_from_u64(21);
+    let points: Vec<Point3> = (0..300)
+        .map(|i| {
+            let vec = Vec3::new(rng.gen(), rng.gen(), rng.gen()) * 100.0;
+            (vec, Entity::from_raw(i)).into()
+        })
+        .collect();
+    let mut tree = KDTree3::<Marker>::default();
+    tree.update(points.iter().map(|p| (*p, true)), std::iter::empty());
+    assert_eq!(tree.tree.len(), points.len());
+    assert_layout(&tree.tree, 0, 3, &|p, axis| p.vec[axis]);
+}
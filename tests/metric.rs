@@ -0,0 +1,183 @@
+//! Compares the queries by [`Metric`] of the KD-trees against brute force over every point.
+
+mod common;
+
+use bevy::{math::IVec3, prelude::*};
+use bevy_spatial::{
+    kdtree::{KDTree2, KDTreeI3},
+    metric::{Chebyshev, Manhattan, Metric, WeightedEuclidean},
+    point::{IPoint3, Point2, SpatialPoint},
+    SpatialMetricAccess, UpdateSpatialAccess,
+};
+use common::{kdtree, random_points, sorted};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(Component)]
+struct Marker;
+
+/// Checks `tree` against the points sorted by `distance` from each of `queries`.
+///
+/// `radius` converts a radius to the unit of `distance`, the same way `metric` does.
+fn check<P, M, T>(
+    tree: &T,
+    points: &[P],
+    queries: &[P::Vec],
+    metric: &M,
+    distance: impl Fn(P::Vec, P::Vec) -> P::Scalar,
+    radius: impl Fn(P::Scalar) -> P::Scalar,
+    radii: &[P::Scalar],
+) where
+    P: SpatialPoint,
+    P::Scalar: std::fmt::Debug,
+    M: Metric<P>,
+    T: SpatialMetricAccess<Point = P, ResultT = (P::Vec, Option<Entity>)>,
+{
+    for loc in queries {
+        let mut by_distance: Vec<_> = points
+            .iter()
+            .map(|p| (distance(*loc, p.vec()), p.entity().unwrap()))
+            .collect();
+        by_distance.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        let (nearest, _) = tree.nearest_neighbour_by(*loc, metric).unwrap();
+        assert_eq!(nearest, by_distance[0].0);
+
+        for k in [1, 7, points.len()] {
+            let found: Vec<_> = tree
+                .k_nearest_neighbour_by(*loc, k, metric)
+                .into_iter()
+                .map(|(d, (vec, _))| {
+                    assert_eq!(d, distance(*loc, vec));
+                    d
+                })
+                .collect();
+            let expected: Vec<_> = by_distance[..k].iter().map(|(d, _)| *d).collect();
+            assert_eq!(found, expected);
+        }
+
+        for r in radii {
+            let found = tree.within_distance_by(*loc, *r, metric);
+            assert!(found.iter().all(|(d, (vec, _))| *d == distance(*loc, *vec)));
+            let expected = by_distance
+                .iter()
+                .filter(|(d, _)| *d <= radius(*r))
+                .map(|(_, e)| *e)
+                .collect();
+            assert_eq!(
+                sorted(found.into_iter().filter_map(|(_, (_, e))| e).collect()),
+                sorted(expected)
+            );
+        }
+    }
+}
+
+fn float_setup() -> (KDTree2<Marker>, Vec<Point2>, Vec<Vec2>) {
+    let points = random_points(30, 300, 0);
+    let mut queries: Vec<_> = random_points(31, 10, 0).iter().map(|p| p.vec).collect();
+    queries.extend(points.iter().step_by(40).map(|p| p.vec));
+    (kdtree(&points), points, queries)
+}
+
+fn integer_setup() -> (KDTreeI3<Marker>, Vec<IPoint3>, Vec<IVec3>) {
+    let mut rng = StdRng::seed_from_u64(32);
+    let mut vec = || {
+        IVec3::new(
+            rng.gen_range(-50..50),
+            rng.gen_range(-50..50),
+            rng.gen_range(-50..50),
+        )
+    };
+    let points: Vec<IPoint3> = (0..300)
+        .map(|i| (vec(), Entity::from_raw(i)).into())
+        .collect();
+    let mut queries: Vec<_> = (0..10).map(|_| vec()).collect();
+    queries.extend(points.iter().step_by(40).map(|p| p.vec));
+    let mut tree = KDTreeI3::default();
+    tree.update(points.iter().map(|p| (*p, true)), std::iter::empty());
+    (tree, points, queries)
+}
+
+#[test]
+fn manhattan() {
+    let (tree, points, queries) = float_setup();
+    let distance = |a: Vec2, b: Vec2| (a - b).abs().element_sum();
+    check(
+        &tree,
+        &points,
+        &queries,
+        &Manhattan,
+        distance,
+        |r| r,
+        &[0.0, 5.0, 30.0],
+    );
+
+    let (tree, points, queries) = integer_setup();
+    let distance = |a: IVec3, b: IVec3| (a - b).abs().element_sum();
+    check(
+        &tree,
+        &points,
+        &queries,
+        &Manhattan,
+        distance,
+        |r| r,
+        &[0, 9, 40],
+    );
+}
+
+#[test]
+fn chebyshev() {
+    let (tree, points, queries) = float_setup();
+    let distance = |a: Vec2, b: Vec2| (a - b).abs().max_element();
+    check(
+        &tree,
+        &points,
+        &queries,
+        &Chebyshev,
+        distance,
+        |r| r,
+        &[0.0, 5.0, 30.0],
+    );
+
+    let (tree, points, queries) = integer_setup();
+    let distance = |a: IVec3, b: IVec3| (a - b).abs().max_element();
+    check(
+        &tree,
+        &points,
+        &queries,
+        &Chebyshev,
+        distance,
+        |r| r,
+        &[0, 9, 40],
+    );
+}
+
+#[test]
+fn weighted_euclidean() {
+    let (tree, points, queries) = float_setup();
+    let weights = Vec2::new(1.0, 4.0);
+    let distance = move |a: Vec2, b: Vec2| ((a - b) * (a - b) * weights).element_sum();
+    let metric = WeightedEuclidean::new(weights);
+    check(
+        &tree,
+        &points,
+        &queries,
+        &metric,
+        distance,
+        |r| r * r,
+        &[0.0, 5.0, 30.0],
+    );
+
+    let (tree, points, queries) = integer_setup();
+    let weights = IVec3::new(1, 4, 9);
+    let distance = move |a: IVec3, b: IVec3| ((a - b) * (a - b) * weights).element_sum();
+    let metric = WeightedEuclidean::new(weights);
+    check(
+        &tree,
+        &points,
+        &queries,
+        &metric,
+        distance,
+        |r| r * r,
+        &[0, 9, 40],
+    );
+}
@@ -0,0 +1,123 @@
+//! Checks the neighbours [`SpatialNeighbours`] keeps for its entity.
+
+mod common;
+
+use bevy::prelude::*;
+use bevy_spatial::{AutomaticUpdate, SpatialNeighbours};
+use common::app;
+
+#[derive(Component)]
+struct Marker;
+
+/// The x coordinates of the tracked entities.
+const XS: [f32; 6] = [0.0, 1.0, 3.0, 7.0, 15.0, 31.0];
+
+/// The x coordinate of the seekers, at distances 0.4, 2.4, 3.4, 3.6, 11.6 and 27.6 to the tracked entities.
+const SEEKER: f32 = 3.4;
+
+/// An app tracking entities at [`XS`], and untracked seekers at [`SEEKER`] with `seekers`.
+fn setup(seekers: Vec<SpatialNeighbours<Marker>>) -> (App, Vec<Entity>, Vec<Entity>) {
+    let mut app = app(AutomaticUpdate::<Marker>::new());
+    let tracked = XS
+        .iter()
+        .map(|x| {
+            app.world_mut()
+                .spawn((Marker, Transform::from_xyz(*x, 0.0, 0.0)))
+                .id()
+        })
+        .collect();
+    let seekers = seekers
+        .into_iter()
+        .map(|seeker| {
+            app.world_mut()
+                .spawn((Transform::from_xyz(SEEKER, 0.0, 0.0), seeker))
+                .id()
+        })
+        .collect();
+    app.update();
+    (app, tracked, seekers)
+}
+
+fn result(app: &App, seeker: Entity) -> Vec<Entity> {
+    app.world()
+        .get::<SpatialNeighbours<Marker>>(seeker)
+        .unwrap()
+        .result()
+        .to_vec()
+}
+
+#[test]
+fn neighbours_are_sorted_and_limited() {
+    let (app, tracked, seekers) = setup(vec![
+        SpatialNeighbours::nearest(3),
+        SpatialNeighbours::within(3.5),
+        SpatialNeighbours::nearest_within(2, 3.5),
+        SpatialNeighbours::nearest_within(5, 3.5),
+        SpatialNeighbours::nearest_within(3, 1.0),
+        SpatialNeighbours::nearest_within(4, 100.0),
+        SpatialNeighbours::nearest_within(3, 0.1),
+    ]);
+    let expected: [&[usize]; 7] = [
+        &[2, 1, 0],
+        &[2, 1, 0],
+        &[2, 1],
+        &[2, 1, 0],
+        &[2],
+        &[2, 1, 0, 3],
+        &[],
+    ];
+    for (seeker, expected) in seekers.iter().zip(expected) {
+        let expected: Vec<_> = expected.iter().map(|i| tracked[*i]).collect();
+        assert_eq!(result(&app, *seeker), expected);
+    }
+}
+
+#[test]
+fn tracked_entities_are_not_their_own_neighbour() {
+    let (mut app, tracked, _) = setup(vec![]);
+    let seeker = app
+        .world_mut()
+        .spawn((
+            Marker,
+            Transform::from_xyz(SEEKER, 0.0, 0.0),
+            SpatialNeighbours::<Marker>::nearest_within(2, 5.0),
+        ))
+        .id();
+    app.update();
+    assert_eq!(result(&app, seeker), vec![tracked[2], tracked[1]]);
+}
+
+#[test]
+fn neighbours_follow_movement() {
+    let (mut app, tracked, seekers) = setup(vec![SpatialNeighbours::nearest(2)]);
+    assert_eq!(result(&app, seekers[0]), vec![tracked[2], tracked[1]]);
+
+    app.world_mut()
+        .entity_mut(seekers[0])
+        .insert(Transform::from_xyz(16.0, 0.0, 0.0));
+    app.world_mut()
+        .entity_mut(tracked[0])
+        .insert(Transform::from_xyz(17.5, 0.0, 0.0));
+    app.update();
+    assert_eq!(result(&app, seekers[0]), vec![tracked[4], tracked[0]]);
+
+    app.world_mut().despawn(tracked[4]);
+    app.update();
+    assert_eq!(result(&app, seekers[0]), vec![tracked[0], tracked[3]]);
+}
+
+#[test]
+fn limits_are_kept() {
+    let neighbours = SpatialNeighbours::<Marker>::nearest_within(4, 2.5);
+    assert_eq!((neighbours.k(), neighbours.radius()), (Some(4), Some(2.5)));
+    let neighbours = SpatialNeighbours::<Marker>::nearest(4);
+    assert_eq!((neighbours.k(), neighbours.radius()), (Some(4), None));
+    let neighbours = SpatialNeighbours::<Marker>::within(2.5);
+    assert_eq!((neighbours.k(), neighbours.radius()), (None, Some(2.5)));
+}
+
+#[test]
+#[should_panic(expected = "the radius of SpatialNeighbours has to be a non-negative number")]
+fn negative_radius_panics() {
+    let _ = SpatialNeighbours::<Marker>::within(-1.0);
+}
@@ -0,0 +1,453 @@
+//! Compares every datastructure against the linear scan, which looks at every point and serves as the reference.
+
+use std::ops::ControlFlow;
+
+use bevy::{
+    math::{DVec2, DVec3, Vec3A},
+    prelude::*,
+};
+use bevy_spatial::{
+    grid::{Grid2, Grid3, Grid3A, GridD2, GridD3},
+    kdtree::{KDTree2, KDTree3, KDTree3A, KDTreeD2, KDTreeD3},
+    linear::{Linear2, Linear3, Linear3A, LinearD2, LinearD3},
+    point::SpatialPoint,
+    SpatialAABBAccess, SpatialAccess, SpatialPairAccess, SpatialRayAccess, UpdateSpatialAccess,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(Component)]
+struct Marker;
+
+/// Number of points stored in each datastructure.
+const POINTS: u32 = 400;
+/// Number of query locations, half of them on stored points.
+const QUERIES: usize = 40;
+/// Side length of the cube the points are placed in.
+const SIZE: f64 = 100.0;
+
+/// Sorted entities of the results.
+fn entities<V>(results: impl IntoIterator<Item = (V, Option<Entity>)>) -> Vec<Entity> {
+    let mut entities: Vec<_> = results.into_iter().filter_map(|(_, e)| e).collect();
+    entities.sort_unstable();
+    entities
+}
+
+/// Sorted entity pairs, with the smaller entity first in each pair.
+fn pairs(pairs: impl IntoIterator<Item = (Entity, Entity)>) -> Vec<(Entity, Entity)> {
+    let mut pairs: Vec<_> = pairs
+        .into_iter()
+        .map(|(a, b)| (a.min(b), a.max(b)))
+        .collect();
+    pairs.sort_unstable();
+    pairs
+}
+
+fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() <= 1e-3 * (1.0 + a.abs()), "{a} != {b}");
+}
+
+fn assert_all_close(a: impl IntoIterator<Item = f64>, b: impl IntoIterator<Item = f64>) {
+    let (a, b): (Vec<_>, Vec<_>) = (a.into_iter().collect(), b.into_iter().collect());
+    assert_eq!(a.len(), b.len(), "{a:?} != {b:?}");
+    for (a, b) in a.into_iter().zip(b) {
+        assert_close(a, b);
+    }
+}
+
+/// Generates tests comparing every query of `$ds` against `$linear`, on the same random points.
+macro_rules! oracle_tests {
+    ($name:ident, $vec:ty, $ds:ty, $new:expr, $linear:ty) => {
+        mod $name {
+            use super::*;
+
+            type V = $vec;
+            type Point = <$ds as SpatialAccess>::Point;
+
+            fn random_vec(rng: &mut StdRng) -> V {
+                let mut v = V::ZERO;
+                for i in 0..Point::default().vec().to_array().len() {
+                    v[i] = (rng.gen::<f64>() * SIZE) as _;
+                }
+                v
+            }
+
+            /// The datastructure under test and the linear scan, filled with the same points, and the query locations.
+            fn setup() -> ($ds, $linear, Vec<V>) {
+                let mut rng = StdRng::seed_from_u64(7);
+                let points: Vec<Point> = (0..POINTS)
+                    .map(|i| (random_vec(&mut rng), Entity::from_raw(i)).into())
+                    .collect();
+                let mut ds: $ds = $new;
+                let mut linear = <$linear>::default();
+                ds.update(points.iter().map(|p| (*p, true)), std::iter::empty());
+                linear.update(points.iter().map(|p| (*p, true)), std::iter::empty());
+                let queries = (0..QUERIES)
+                    .map(|i| {
+                        if i % 2 == 0 {
+                            points[i * 7].vec
+                        } else {
+                            random_vec(&mut rng)
+                        }
+                    })
+                    .collect();
+                (ds, linear, queries)
+            }
+
+            fn distance(a: V, b: V) -> f64 {
+                f64::from(Point::from(a).distance_squared(&b.into()))
+            }
+
+            #[test]
+            fn nearest_neighbour() {
+                let (ds, linear, queries) = setup();
+                for loc in queries {
+                    let found = ds.nearest_neighbour(loc).unwrap();
+                    let expected = linear.nearest_neighbour(loc).unwrap();
+                    assert_close(distance(loc, found.0), distance(loc, expected.0));
+
+                    let (d, found) = ds.nearest_neighbour_with_distance(loc).unwrap();
+                    assert_close(f64::from(d), distance(loc, found.0));
+                }
+            }
+
+            #[test]
+            fn k_nearest_neighbour() {
+                let (ds, linear, queries) = setup();
+                let mut out = vec![];
+                for loc in queries {
+                    for k in [0, 1, 5, 32, POINTS as usize + 5] {
+                        let expected = linear.k_nearest_neighbour(loc, k);
+                        let expected_distances: Vec<_> =
+                            expected.iter().map(|r| distance(loc, r.0)).collect();
+
+                        let found = ds.k_nearest_neighbour(loc, k);
+                        assert_all_close(
+                            found.iter().map(|r| distance(loc, r.0)),
+                            expected_distances.iter().copied(),
+                        );
+                        assert_eq!(entities(found), entities(expected.iter().copied()));
+
+                        let found = ds.k_nearest_neighbour_with_distance(loc, k);
+                        assert_all_close(
+                            found.iter().map(|(d, _)| f64::from(*d)),
+                            expected_distances.iter().copied(),
+                        );
+
+                        // appends behind what is already in the buffer
+                        out.clear();
+                        out.push((V::ZERO, None));
+                        ds.k_nearest_neighbour_into(loc, k, &mut out);
+                        assert_eq!(out[0], (V::ZERO, None));
+                        assert_all_close(
+                            out[1..].iter().map(|r| distance(loc, r.0)),
+                            expected_distances.iter().copied(),
+                        );
+
+                        let mut visited = vec![];
+                        ds.for_each_k_nearest_neighbour(loc, k, |r| {
+                            visited.push(distance(loc, r.0));
+                            ControlFlow::Continue(())
+                        });
+                        assert_all_close(visited, expected_distances.iter().copied());
+
+                        let mut visited = 0;
+                        ds.for_each_k_nearest_neighbour(loc, k, |_| {
+                            visited += 1;
+                            ControlFlow::Break(())
+                        });
+                        assert_eq!(visited, k.min(1));
+                    }
+                }
+            }
+
+            #[test]
+            fn filtered() {
+                let (ds, linear, queries) = setup();
+                let odd = |e: Entity| e.index() % 2 == 1;
+                for loc in queries {
+                    let found = ds.nearest_neighbour_filtered(loc, odd).unwrap();
+                    let expected = linear.nearest_neighbour_filtered(loc, odd).unwrap();
+                    assert!(odd(found.1.unwrap()));
+                    assert_close(distance(loc, found.0), distance(loc, expected.0));
+
+                    for k in [1, 10, POINTS as usize] {
+                        let found = ds.k_nearest_neighbour_filtered_with_distance(loc, k, odd);
+                        let expected = linear.k_nearest_neighbour_filtered(loc, k, odd);
+                        assert!(found.iter().all(|(_, r)| odd(r.1.unwrap())));
+                        assert_all_close(
+                            found.iter().map(|(d, _)| f64::from(*d)),
+                            expected.iter().map(|r| distance(loc, r.0)),
+                        );
+                    }
+                }
+            }
+
+            #[test]
+            fn within_distance() {
+                let (ds, linear, queries) = setup();
+                let mut out = vec![];
+                for loc in queries {
+                    for radius in [0.0, 3.0, 15.0, 40.0] {
+                        let expected = entities(linear.within_distance(loc, radius));
+
+                        assert_eq!(entities(ds.within_distance(loc, radius)), expected);
+
+                        out.clear();
+                        ds.within_distance_into(loc, radius, &mut out);
+                        assert_eq!(entities(out.iter().copied()), expected);
+
+                        let mut visited = vec![];
+                        ds.for_each_within_distance(loc, radius, |r| {
+                            visited.push(r);
+                            ControlFlow::Continue(())
+                        });
+                        assert_eq!(entities(visited), expected);
+
+                        let mut visited = 0;
+                        ds.for_each_within_distance(loc, radius, |_| {
+                            visited += 1;
+                            ControlFlow::Break(())
+                        });
+                        assert_eq!(visited, expected.len().min(1));
+
+                        let sorted = ds.within_distance_sorted(loc, radius);
+                        assert!(sorted.windows(2).all(|w| w[0].0 <= w[1].0));
+                        assert_eq!(entities(sorted.iter().map(|(_, r)| *r)), expected);
+                        for (d, r) in sorted {
+                            assert_close(f64::from(d), distance(loc, r.0));
+                        }
+
+                        for k in [1, 4, POINTS as usize] {
+                            let found = ds.k_nearest_within(loc, k, radius);
+                            let nearest = linear.k_nearest_neighbour(loc, k);
+                            let expected = nearest
+                                .iter()
+                                .filter(|r| distance(loc, r.0) <= f64::from(radius * radius));
+                            assert_eq!(entities(found), entities(expected.copied()));
+                        }
+                    }
+                }
+            }
+
+            #[test]
+            fn within_aabb() {
+                let (ds, linear, queries) = setup();
+                for (a, b) in queries.iter().zip(queries.iter().skip(1)) {
+                    // the corners can be given in any order
+                    assert_eq!(
+                        entities(ds.within_aabb(*a, *b)),
+                        entities(linear.within_aabb(a.min(*b), a.max(*b)))
+                    );
+                }
+            }
+
+            #[test]
+            fn cast_ray() {
+                let (ds, linear, queries) = setup();
+                for (origin, target) in queries.iter().zip(queries.iter().skip(1)) {
+                    let direction = (*target - *origin).normalize();
+                    for radius in [0.5, 4.0] {
+                        let found = ds.cast_ray(*origin, direction, 60.0, radius);
+                        let expected = linear.cast_ray(*origin, direction, 60.0, radius);
+                        assert!(found.windows(2).all(|w| w[0].0 <= w[1].0));
+                        assert_eq!(
+                            entities(found.iter().map(|(_, r)| *r)),
+                            entities(expected.iter().map(|(_, r)| *r))
+                        );
+                        let mut found_t: Vec<_> =
+                            found.iter().map(|(t, _)| f64::from(*t)).collect();
+                        let mut expected_t: Vec<_> =
+                            expected.iter().map(|(t, _)| f64::from(*t)).collect();
+                        found_t.sort_by(f64::total_cmp);
+                        expected_t.sort_by(f64::total_cmp);
+                        assert_all_close(found_t, expected_t);
+                    }
+                }
+            }
+
+            #[test]
+            fn pairs_within_distance() {
+                let (ds, linear, _) = setup();
+                let entity_pairs = |found: Vec<(Point, Point)>| {
+                    pairs(
+                        found
+                            .into_iter()
+                            .map(|(a, b)| (a.entity.unwrap(), b.entity.unwrap())),
+                    )
+                };
+                for radius in [0.0, 2.0, 9.0] {
+                    let expected = entity_pairs(linear.pairs_within_distance(radius));
+                    assert_eq!(entity_pairs(ds.pairs_within_distance(radius)), expected);
+                    assert_eq!(entity_pairs(ds.pairs_within_distance_par(radius)), expected);
+                }
+            }
+
+            #[test]
+            fn join() {
+                let (ds, linear, _) = setup();
+                let mut rng = StdRng::seed_from_u64(11);
+                let mut other = <$linear>::default();
+                other.update(
+                    (0..POINTS / 4).map(|i| {
+                        let p: Point = (random_vec(&mut rng), Entity::from_raw(POINTS + i)).into();
+                        (p, true)
+                    }),
+                    std::iter::empty(),
+                );
+
+                let radius = 6.0;
+                let mut expected = vec![];
+                for a in linear.iter_points() {
+                    for b in other.iter_points() {
+                        if a.distance_squared(b) <= radius * radius {
+                            expected.push((a.entity.unwrap(), b.entity.unwrap()));
+                        }
+                    }
+                }
+                let mut found = vec![];
+                ds.join_within_distance(&other, radius, |a, b| {
+                    found.push((a.entity.unwrap(), b.1.unwrap()));
+                });
+                assert_eq!(pairs(found), pairs(expected));
+
+                for (a, nearest) in ds.join_nearest(&other) {
+                    let expected = other.nearest_neighbour(a.vec).unwrap();
+                    assert_close(
+                        distance(a.vec, nearest.unwrap().0),
+                        distance(a.vec, expected.0),
+                    );
+                }
+            }
+
+            #[test]
+            fn removed_points() {
+                let (mut ds, mut linear, queries) = setup();
+                for i in (0..POINTS).step_by(3) {
+                    ds.remove_entity(Entity::from_raw(i));
+                    linear.remove_entity(Entity::from_raw(i));
+                }
+                for loc in queries {
+                    assert_eq!(
+                        entities(ds.within_distance(loc, 20.0)),
+                        entities(linear.within_distance(loc, 20.0))
+                    );
+                    assert_eq!(
+                        entities(ds.k_nearest_neighbour(loc, 8)),
+                        entities(linear.k_nearest_neighbour(loc, 8))
+                    );
+                }
+
+                ds.clear();
+                assert!(ds.nearest_neighbour(V::ZERO).is_none());
+                assert!(ds.within_distance(V::ZERO, SIZE as _).is_empty());
+            }
+        }
+    };
+}
+
+oracle_tests!(
+    kdtree2,
+    Vec2,
+    KDTree2<Marker>,
+    KDTree2::default(),
+    Linear2<Marker>
+);
+oracle_tests!(
+    kdtree3,
+    Vec3,
+    KDTree3<Marker>,
+    KDTree3::default(),
+    Linear3<Marker>
+);
+oracle_tests!(
+    kdtree3a,
+    Vec3A,
+    KDTree3A<Marker>,
+    KDTree3A::default(),
+    Linear3A<Marker>
+);
+oracle_tests!(
+    kdtreed2,
+    DVec2,
+    KDTreeD2<Marker>,
+    KDTreeD2::default(),
+    LinearD2<Marker>
+);
+oracle_tests!(
+    kdtreed3,
+    DVec3,
+    KDTreeD3<Marker>,
+    KDTreeD3::default(),
+    LinearD3<Marker>
+);
+oracle_tests!(grid2, Vec2, Grid2<Marker>, Grid2::new(7.0), Linear2<Marker>);
+oracle_tests!(
+    grid3,
+    Vec3,
+    Grid3<Marker>,
+    Grid3::new(12.0),
+    Linear3<Marker>
+);
+oracle_tests!(
+    grid3a,
+    Vec3A,
+    Grid3A<Marker>,
+    Grid3A::new(12.0),
+    Linear3A<Marker>
+);
+oracle_tests!(
+    gridd2,
+    DVec2,
+    GridD2<Marker>,
+    GridD2::new(7.0),
+    LinearD2<Marker>
+);
+oracle_tests!(
+    gridd3,
+    DVec3,
+    GridD3<Marker>,
+    GridD3::new(12.0),
+    LinearD3<Marker>
+);
+
+#[cfg(feature = "rstar")]
+mod rstar {
+    use super::*;
+    use bevy_spatial::rtree::{RTree2, RTree3, RTree3A, RTreeD2, RTreeD3};
+
+    oracle_tests!(
+        rtree2,
+        Vec2,
+        RTree2<Marker>,
+        RTree2::default(),
+        Linear2<Marker>
+    );
+    oracle_tests!(
+        rtree3,
+        Vec3,
+        RTree3<Marker>,
+        RTree3::default(),
+        Linear3<Marker>
+    );
+    oracle_tests!(
+        rtree3a,
+        Vec3A,
+        RTree3A<Marker>,
+        RTree3A::default(),
+        Linear3A<Marker>
+    );
+    oracle_tests!(
+        rtreed2,
+        DVec2,
+        RTreeD2<Marker>,
+        RTreeD2::default(),
+        LinearD2<Marker>
+    );
+    oracle_tests!(
+        rtreed3,
+        DVec3,
+        RTreeD3<Marker>,
+        RTreeD3::default(),
+        LinearD3<Marker>
+    );
+}
@@ -0,0 +1,130 @@
+//! Checks the events and triggers sent for entities getting within the radius of a [`ProximityWatcher`].
+
+mod common;
+
+use bevy::prelude::*;
+use bevy_spatial::{AutomaticUpdate, EnteredProximity, ExitedProximity, ProximityWatcher};
+use common::app;
+
+#[derive(Component)]
+struct Marker;
+
+/// The `(watcher, other)` pairs triggered on the watcher, entered ones first.
+#[derive(Resource, Default)]
+struct Triggered(Vec<(Entity, Entity)>, Vec<(Entity, Entity)>);
+
+/// Drains the events and triggers sent since the last call, entered ones first.
+fn sent(app: &mut App) -> [Vec<(Entity, Entity)>; 4] {
+    let world = app.world_mut();
+    let entered = world
+        .resource_mut::<Events<EnteredProximity>>()
+        .drain()
+        .map(|e| (e.watcher, e.other))
+        .collect();
+    let exited = world
+        .resource_mut::<Events<ExitedProximity>>()
+        .drain()
+        .map(|e| (e.watcher, e.other))
+        .collect();
+    let triggered = std::mem::take(&mut *world.resource_mut::<Triggered>());
+    [entered, exited, triggered.0, triggered.1]
+}
+
+fn setup() -> (App, Entity) {
+    let mut app = app(AutomaticUpdate::<Marker>::new());
+    app.init_resource::<Triggered>();
+    // the watcher is tracked too, but never reports itself
+    let watcher = app
+        .world_mut()
+        .spawn((
+            Marker,
+            Transform::default(),
+            ProximityWatcher::<Marker>::new(5.0),
+        ))
+        .observe(
+            |trigger: Trigger<EnteredProximity>, mut triggered: ResMut<Triggered>| {
+                assert_eq!(trigger.entity(), trigger.watcher);
+                triggered.0.push((trigger.watcher, trigger.other));
+            },
+        )
+        .observe(
+            |trigger: Trigger<ExitedProximity>, mut triggered: ResMut<Triggered>| {
+                assert_eq!(trigger.entity(), trigger.watcher);
+                triggered.1.push((trigger.watcher, trigger.other));
+            },
+        )
+        .id();
+    app.update();
+    (app, watcher)
+}
+
+#[test]
+fn entering_and_leaving_the_radius_is_sent() {
+    let (mut app, watcher) = setup();
+    let other = app
+        .world_mut()
+        .spawn((Marker, Transform::from_xyz(10.0, 0.0, 0.0)))
+        .id();
+    app.update();
+    assert_eq!(sent(&mut app), [vec![], vec![], vec![], vec![]]);
+
+    app.world_mut()
+        .entity_mut(other)
+        .insert(Transform::from_xyz(3.0, 4.0, 0.0));
+    app.update();
+    let pair = vec![(watcher, other)];
+    assert_eq!(sent(&mut app), [pair.clone(), vec![], pair.clone(), vec![]]);
+    let inside: Vec<_> = app
+        .world()
+        .get::<ProximityWatcher<Marker>>(watcher)
+        .unwrap()
+        .inside()
+        .collect();
+    assert_eq!(inside, vec![other]);
+
+    // staying inside sends nothing
+    app.world_mut()
+        .entity_mut(other)
+        .insert(Transform::from_xyz(0.0, 1.0, 0.0));
+    app.update();
+    assert_eq!(sent(&mut app), [vec![], vec![], vec![], vec![]]);
+
+    app.world_mut()
+        .entity_mut(other)
+        .insert(Transform::from_xyz(0.0, 5.5, 0.0));
+    app.update();
+    assert_eq!(sent(&mut app), [vec![], pair.clone(), vec![], pair]);
+}
+
+#[test]
+fn despawned_entities_exit() {
+    let (mut app, watcher) = setup();
+    let other = app
+        .world_mut()
+        .spawn((Marker, Transform::from_xyz(1.0, 0.0, 0.0)))
+        .id();
+    app.update();
+    let pair = vec![(watcher, other)];
+    assert_eq!(sent(&mut app), [pair.clone(), vec![], pair.clone(), vec![]]);
+
+    app.world_mut().despawn(other);
+    app.update();
+    assert_eq!(sent(&mut app), [vec![], pair.clone(), vec![], pair]);
+}
+
+#[test]
+fn radius_is_kept() {
+    assert_eq!(ProximityWatcher::<Marker>::new(2.5).radius(), 2.5);
+}
+
+#[test]
+#[should_panic(expected = "the radius of ProximityWatcher has to be a non-negative number")]
+fn negative_radius_panics() {
+    let _ = ProximityWatcher::<Marker>::new(-1.0);
+}
+
+#[test]
+#[should_panic(expected = "the radius of ProximityWatcher has to be a non-negative number")]
+fn nan_radius_panics() {
+    let _ = ProximityWatcher::<Marker>::new(f32::NAN);
+}
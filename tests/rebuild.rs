@@ -0,0 +1,72 @@
+//! Checks that unchanged datastructures are neither rebuilt nor marked as changed.
+
+mod common;
+
+use bevy::prelude::*;
+use bevy_spatial::{
+    kdtree::{KDTree2, KDTree3},
+    AutomaticUpdate, SpatialAccess, SpatialStructure, UpdateSpatialAccess,
+};
+use common::{app, random_points};
+
+#[derive(Component)]
+struct Marker;
+
+#[test]
+fn rebuilds_only_on_changes() {
+    let points = random_points(11, 50, 0);
+    let mut tree = KDTree2::<Marker>::default();
+    assert!(tree.update(points.iter().map(|p| (*p, true)), std::iter::empty()));
+    assert!(tree.rebuilt_last_update());
+
+    assert!(!tree.update(points.iter().map(|p| (*p, false)), std::iter::empty()));
+    assert!(!tree.rebuilt_last_update());
+
+    // a point which appeared without being reported as changed still triggers a rebuild
+    assert!(tree.update(
+        points.iter().chain(&points[..1]).map(|p| (*p, false)),
+        std::iter::empty(),
+    ));
+    assert!(tree.rebuilt_last_update());
+
+    tree.clear();
+    assert!(!tree.rebuilt_last_update());
+    assert!(tree.nearest_neighbour(Vec2::ZERO).is_none());
+}
+
+/// Number of frames in which the datastructure of [`Marker`] was marked as changed.
+#[derive(Resource, Default)]
+struct Changes(usize);
+
+#[test]
+fn resource_is_only_changed_when_entities_moved() {
+    let mut app = app(AutomaticUpdate::<Marker>::new().with_spatial_ds(SpatialStructure::KDTree3));
+    app.init_resource::<Changes>().add_systems(
+        PostUpdate,
+        (|mut changes: ResMut<Changes>| changes.0 += 1).run_if(resource_changed::<KDTree3<Marker>>),
+    );
+    let entity = app
+        .world_mut()
+        .spawn((Marker, Transform::from_xyz(1.0, 2.0, 3.0)))
+        .id();
+    app.update();
+    let changes = app.world().resource::<Changes>().0;
+
+    for _ in 0..3 {
+        app.update();
+    }
+    assert_eq!(app.world().resource::<Changes>().0, changes);
+
+    app.world_mut()
+        .get_mut::<Transform>(entity)
+        .unwrap()
+        .translation
+        .x = 5.0;
+    app.update();
+    assert_eq!(app.world().resource::<Changes>().0, changes + 1);
+    let tree = app.world().resource::<KDTree3<Marker>>();
+    assert_eq!(
+        tree.nearest_neighbour(Vec3::ZERO).unwrap().0,
+        Vec3::new(5.0, 2.0, 3.0)
+    );
+}
@@ -0,0 +1,61 @@
+//! Incremental updates of the R*-trees, checked against brute force.
+#![cfg(feature = "rstar")]
+
+mod common;
+
+use bevy::prelude::*;
+use bevy_spatial::{rtree::RTree2, SpatialAccess, UpdateSpatialAccess};
+use common::{entities, nearest, random_points, within};
+
+#[derive(Component)]
+struct Marker;
+
+#[test]
+fn moved_and_removed_entities_are_updated() {
+    let mut points = random_points(20, 300, 0);
+    let mut tree = RTree2::<Marker>::default();
+    tree.update(points.iter().map(|p| (*p, true)), std::iter::empty());
+    assert_eq!(tree.tree().size(), points.len());
+
+    // move every third entity, and despawn every seventh which did not move
+    let moved = random_points(21, 300, 0);
+    let mut changed = vec![false; points.len()];
+    for i in (0..points.len()).step_by(3) {
+        points[i] = moved[i];
+        changed[i] = true;
+    }
+    let removed: Vec<_> = (0..points.len())
+        .step_by(7)
+        .filter(|i| !changed[*i])
+        .collect();
+    let despawned = removed
+        .iter()
+        .map(|i| points[*i].entity.unwrap())
+        .collect::<Vec<_>>();
+    let live: Vec<_> = points
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !removed.contains(i))
+        .map(|(i, p)| (*p, changed[i]))
+        .collect();
+    tree.update(live.iter().copied(), despawned.iter().copied());
+
+    let live: Vec<_> = live.into_iter().map(|(p, _)| p).collect();
+    assert_eq!(tree.tree().size(), live.len());
+    for loc in [Vec2::ZERO, Vec2::splat(50.0), moved[0].vec, moved[14].vec] {
+        assert_eq!(tree.nearest_neighbour(loc).unwrap().1, nearest(&live, loc));
+        assert_eq!(
+            entities(tree.within_distance(loc, 12.0)),
+            within(&live, loc, 12.0)
+        );
+        assert!(tree
+            .k_nearest_neighbour(loc, 30)
+            .iter()
+            .all(|(_, e)| !despawned.contains(&e.unwrap())));
+    }
+
+    // removing an entity a second time finds nothing
+    assert!(!tree.remove_entity(despawned[0]));
+    assert!(tree.remove_entity(live[0].entity.unwrap()));
+    assert_eq!(tree.tree().size(), live.len() - 1);
+}
@@ -0,0 +1,245 @@
+//! Checks that entities with [`SamplePoints`] are tracked at each of their points, and found once by the per-entity queries.
+
+mod common;
+
+use std::time::Duration;
+
+use bevy::{
+    math::{DVec3, I64Vec3},
+    prelude::*,
+};
+use bevy_spatial::{
+    kdtree::{KDTree3, KDTreeD3, KDTreeI2},
+    point::{SamplePoints, SpatialCoordinate, SpatialGridCell, SpatialPoint, SpatialTilePosition},
+    AutomaticUpdate, SpatialAccess, SpatialPairAccess, SpatialStructure,
+};
+use common::app;
+
+#[derive(Component)]
+struct Marker;
+
+#[derive(Component)]
+struct SimPosition(DVec3);
+
+impl SpatialCoordinate for SimPosition {
+    fn coordinate(&self) -> DVec3 {
+        self.0
+    }
+}
+
+#[derive(Component)]
+struct GridCell(IVec3);
+
+impl SpatialGridCell for GridCell {
+    fn cell_origin(&self) -> DVec3 {
+        self.0.as_dvec3() * 1000.0
+    }
+}
+
+#[derive(Component)]
+struct TilePos(IVec2);
+
+impl SpatialTilePosition for TilePos {
+    fn tile_position(&self) -> I64Vec3 {
+        self.0.extend(0).as_i64vec3()
+    }
+}
+
+/// The locations tracked for `entity`, sorted along every axis.
+fn tracked<DS>(app: &App, entity: Entity) -> Vec<<DS::Point as SpatialPoint>::Vec>
+where
+    DS: SpatialPairAccess + Resource,
+    <DS::Point as SpatialPoint>::Vec: Into<DVec3>,
+{
+    let mut found: Vec<_> = app
+        .world()
+        .resource::<DS>()
+        .iter_points()
+        .filter(|p| p.entity() == Some(entity))
+        .map(SpatialPoint::vec)
+        .collect();
+    found.sort_by(|a, b| {
+        let (a, b): (DVec3, DVec3) = ((*a).into(), (*b).into());
+        a.to_array().partial_cmp(&b.to_array()).unwrap()
+    });
+    found
+}
+
+fn samples() -> SamplePoints {
+    SamplePoints(vec![Vec3::ZERO, Vec3::X, Vec3::new(0.0, 0.0, -2.0)])
+}
+
+#[test]
+fn samples_are_transformed() {
+    let mut app = app(AutomaticUpdate::<Marker>::new());
+    let transform = Transform::from_xyz(10.0, 0.0, 0.0)
+        .with_rotation(Quat::from_rotation_y(std::f32::consts::FRAC_PI_2))
+        .with_scale(Vec3::splat(2.0));
+    let entity = app.world_mut().spawn((Marker, transform, samples())).id();
+    app.update();
+
+    let found = tracked::<KDTree3<Marker>>(&app, entity);
+    let expected = [
+        Vec3::new(6.0, 0.0, 0.0),
+        Vec3::new(10.0, 0.0, -2.0),
+        Vec3::new(10.0, 0.0, 0.0),
+    ];
+    assert_eq!(found.len(), expected.len());
+    for (found, expected) in found.iter().zip(expected) {
+        assert!(found.distance(expected) < 1e-5, "{found} != {expected}");
+    }
+}
+
+#[test]
+fn changed_and_removed_samples_are_updated() {
+    // updates only happen every few frames, removals in between must not be missed
+    let mut app = app(AutomaticUpdate::<Marker>::new().with_frequency(Duration::from_millis(350)));
+    let entity = app
+        .world_mut()
+        .spawn((Marker, Transform::from_xyz(5.0, 0.0, 0.0), samples()))
+        .id();
+    let update = |app: &mut App| {
+        for _ in 0..4 {
+            app.update();
+        }
+    };
+    update(&mut app);
+    assert_eq!(tracked::<KDTree3<Marker>>(&app, entity).len(), 3);
+
+    app.world_mut()
+        .entity_mut(entity)
+        .insert(SamplePoints(vec![Vec3::Y]));
+    update(&mut app);
+    assert_eq!(
+        tracked::<KDTree3<Marker>>(&app, entity),
+        vec![Vec3::new(5.0, 1.0, 0.0)]
+    );
+
+    app.world_mut().entity_mut(entity).remove::<SamplePoints>();
+    app.update();
+    update(&mut app);
+    assert_eq!(
+        tracked::<KDTree3<Marker>>(&app, entity),
+        vec![Vec3::new(5.0, 0.0, 0.0)]
+    );
+}
+
+#[test]
+fn custom_coordinates_add_the_samples() {
+    let mut app = app(AutomaticUpdate::<Marker>::new()
+        .with_spatial_ds(SpatialStructure::KDTreeD3)
+        .with_custom_coordinate::<SimPosition>());
+    let position = DVec3::new(1e9 + 0.25, 0.0, 3.0);
+    let entity = app
+        .world_mut()
+        .spawn((Marker, SimPosition(position), samples()))
+        .id();
+    app.update();
+    assert_eq!(
+        tracked::<KDTreeD3<Marker>>(&app, entity),
+        vec![
+            position + DVec3::new(0.0, 0.0, -2.0),
+            position,
+            position + DVec3::X,
+        ]
+    );
+}
+
+#[test]
+fn grid_cells_transform_the_samples_inside_of_the_cell() {
+    let mut app = app(AutomaticUpdate::<Marker>::new()
+        .with_spatial_ds(SpatialStructure::KDTreeD3)
+        .with_grid_cell::<GridCell>());
+    let entity = app
+        .world_mut()
+        .spawn((
+            Marker,
+            GridCell(IVec3::new(2, 0, 0)),
+            Transform::from_xyz(0.5, 0.0, 0.0).with_scale(Vec3::splat(3.0)),
+            samples(),
+        ))
+        .id();
+    app.update();
+    assert_eq!(
+        tracked::<KDTreeD3<Marker>>(&app, entity),
+        vec![
+            DVec3::new(2000.5, 0.0, -6.0),
+            DVec3::new(2000.5, 0.0, 0.0),
+            DVec3::new(2003.5, 0.0, 0.0),
+        ]
+    );
+}
+
+#[test]
+fn tile_samples_are_given_in_tiles() {
+    let mut app = app(AutomaticUpdate::<Marker>::new()
+        .with_spatial_ds(SpatialStructure::KDTreeI2)
+        .with_tile_position::<TilePos>());
+    let entity = app
+        .world_mut()
+        .spawn((
+            Marker,
+            TilePos(IVec2::new(4, -7)),
+            SamplePoints(vec![Vec3::ZERO, Vec3::new(1.0, 2.0, 5.0)]),
+        ))
+        .id();
+    app.update();
+    let found: Vec<_> = app
+        .world()
+        .resource::<KDTreeI2<Marker>>()
+        .iter_points()
+        .filter(|p| p.entity == Some(entity))
+        .map(|p| p.vec)
+        .collect();
+    assert_eq!(found.len(), 2);
+    assert!(found.contains(&IVec2::new(4, -7)));
+    assert!(found.contains(&IVec2::new(5, -5)));
+}
+
+#[test]
+fn per_entity_queries_return_each_entity_once() {
+    let mut app = app(AutomaticUpdate::<Marker>::new());
+    // a train along the z axis, and two single entities next to its front and back
+    let train = app
+        .world_mut()
+        .spawn((
+            Marker,
+            Transform::default(),
+            SamplePoints(
+                (0..5)
+                    .map(|i| Vec3::new(0.0, 0.0, i as f32 * -10.0))
+                    .collect(),
+            ),
+        ))
+        .id();
+    let front = app
+        .world_mut()
+        .spawn((Marker, Transform::from_xyz(3.0, 0.0, 0.0)))
+        .id();
+    let back = app
+        .world_mut()
+        .spawn((Marker, Transform::from_xyz(2.0, 0.0, -40.0)))
+        .id();
+    app.update();
+    let tree = app.world().resource::<KDTree3<Marker>>();
+    let entities = |found: Vec<(f32, (Vec3, Option<Entity>))>| -> Vec<(f32, Entity)> {
+        found
+            .into_iter()
+            .map(|(d, (_, e))| (d, e.unwrap()))
+            .collect()
+    };
+
+    // the points alone would return the train several times
+    assert_eq!(
+        entities(tree.k_nearest_entities(Vec3::new(1.0, 0.0, -1.0), 2)),
+        vec![(2.0, train), (5.0, front)]
+    );
+    assert_eq!(
+        entities(tree.k_nearest_entities(Vec3::new(0.0, 0.0, -36.0), 5)),
+        vec![(16.0, train), (20.0, back), (1305.0, front)]
+    );
+    assert_eq!(
+        entities(tree.within_distance_entities(Vec3::new(0.0, 0.0, -38.0), 4.0)),
+        vec![(4.0, train), (8.0, back)]
+    );
+}
@@ -0,0 +1,167 @@
+//! Checks that [`AutomaticUpdate`] takes the coordinates of tracked entities from the configured source.
+
+mod common;
+
+use bevy::{
+    math::{DVec2, DVec3, I64Vec3},
+    prelude::*,
+};
+use bevy_spatial::{
+    kdtree::{KDTreeD2, KDTreeD3, KDTreeI2},
+    point::{SpatialCoordinate, SpatialGridCell, SpatialPoint, SpatialTilePosition},
+    AutomaticUpdate, SpatialPairAccess, SpatialStructure,
+};
+use common::app;
+
+#[derive(Component)]
+struct Marker;
+
+#[derive(Component)]
+struct SimPosition(DVec3);
+
+impl SpatialCoordinate for SimPosition {
+    fn coordinate(&self) -> DVec3 {
+        self.0
+    }
+}
+
+#[derive(Component)]
+struct GridCell(IVec3);
+
+impl SpatialGridCell for GridCell {
+    fn cell_origin(&self) -> DVec3 {
+        self.0.as_dvec3() * 1_000_000.0
+    }
+}
+
+#[derive(Component)]
+struct TilePos(IVec2);
+
+impl SpatialTilePosition for TilePos {
+    fn tile_position(&self) -> I64Vec3 {
+        self.0.extend(7).as_i64vec3()
+    }
+}
+
+/// The tracked entities and their locations, sorted by entity.
+fn tracked<DS>(app: &App) -> Vec<(Entity, <DS::Point as SpatialPoint>::Vec)>
+where
+    DS: SpatialPairAccess + Resource,
+{
+    let mut found: Vec<_> = app
+        .world()
+        .resource::<DS>()
+        .iter_points()
+        .map(|p| (p.entity().unwrap(), p.vec()))
+        .collect();
+    found.sort_by_key(|(e, _)| *e);
+    found
+}
+
+#[test]
+fn custom_coordinates_keep_double_precision() {
+    let mut app = app(AutomaticUpdate::<Marker>::new()
+        .with_spatial_ds(SpatialStructure::KDTreeD3)
+        .with_custom_coordinate::<SimPosition>());
+    let coordinates = [
+        DVec3::new(1e9 + 0.25, -3.5, 0.125),
+        DVec3::new(-2e12, 1e-3, 4e10 + 0.5),
+    ];
+    let entities: Vec<_> = coordinates
+        .iter()
+        .map(|c| app.world_mut().spawn((Marker, SimPosition(*c))).id())
+        .collect();
+    app.update();
+    assert_eq!(
+        tracked::<KDTreeD3<Marker>>(&app),
+        vec![(entities[0], coordinates[0]), (entities[1], coordinates[1])]
+    );
+
+    app.world_mut()
+        .entity_mut(entities[0])
+        .insert(SimPosition(DVec3::splat(5e9)));
+    app.update();
+    assert_eq!(
+        tracked::<KDTreeD3<Marker>>(&app)[0],
+        (entities[0], DVec3::splat(5e9))
+    );
+}
+
+#[test]
+fn grid_cells_add_the_offset_inside_of_the_cell() {
+    let mut app = app(AutomaticUpdate::<Marker>::new()
+        .with_spatial_ds(SpatialStructure::KDTreeD3)
+        .with_grid_cell::<GridCell>());
+    let a = app
+        .world_mut()
+        .spawn((
+            Marker,
+            GridCell(IVec3::new(3, -2, 0)),
+            Transform::from_xyz(0.5, 1.25, -2.0),
+        ))
+        .id();
+    let b = app
+        .world_mut()
+        .spawn((
+            Marker,
+            GridCell(IVec3::ZERO),
+            Transform::from_xyz(4.0, 0.0, 8.0),
+        ))
+        .id();
+    app.update();
+    assert_eq!(
+        tracked::<KDTreeD3<Marker>>(&app),
+        vec![
+            (a, DVec3::new(3_000_000.5, -1_999_998.75, -2.0)),
+            (b, DVec3::new(4.0, 0.0, 8.0)),
+        ]
+    );
+
+    // moving inside of the cell and changing the cell both update the location
+    app.world_mut()
+        .entity_mut(a)
+        .insert(Transform::from_xyz(1.0, 0.0, 0.0));
+    app.world_mut().entity_mut(b).insert(GridCell(IVec3::X));
+    app.update();
+    assert_eq!(
+        tracked::<KDTreeD3<Marker>>(&app),
+        vec![
+            (a, DVec3::new(3_000_001.0, -2_000_000.0, 0.0)),
+            (b, DVec3::new(1_000_004.0, 0.0, 8.0)),
+        ]
+    );
+}
+
+#[test]
+fn tile_positions_drop_the_third_axis() {
+    let mut app = app(AutomaticUpdate::<Marker>::new()
+        .with_spatial_ds(SpatialStructure::KDTreeI2)
+        .with_tile_position::<TilePos>());
+    let a = app
+        .world_mut()
+        .spawn((Marker, TilePos(IVec2::new(-4, 9))))
+        .id();
+    let b = app
+        .world_mut()
+        .spawn((Marker, TilePos(IVec2::new(120, 0))))
+        .id();
+    app.update();
+    assert_eq!(
+        tracked::<KDTreeI2<Marker>>(&app),
+        vec![(a, IVec2::new(-4, 9)), (b, IVec2::new(120, 0))]
+    );
+}
+
+#[test]
+fn double_precision_trees_read_transforms() {
+    let mut app = app(AutomaticUpdate::<Marker>::new().with_spatial_ds(SpatialStructure::KDTreeD2));
+    let a = app
+        .world_mut()
+        .spawn((Marker, Transform::from_xyz(1.5, -2.25, 9.0)))
+        .id();
+    app.update();
+    assert_eq!(
+        tracked::<KDTreeD2<Marker>>(&app),
+        vec![(a, DVec2::new(1.5, -2.25))]
+    );
+}
@@ -0,0 +1,99 @@
+//! Checks that [`SpatialQuery`] returns the query items of the entities found by the datastructure.
+
+mod common;
+
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use bevy_spatial::{kdtree::KDTree3, AutomaticUpdate, SpatialQuery};
+use common::app;
+
+#[derive(Component)]
+struct Marker;
+
+#[derive(Component)]
+struct Health(u32);
+
+type Query<'w, 's> = SpatialQuery<'w, 's, KDTree3<Marker>, &'static Health>;
+
+/// An app tracking entities on the x axis, every second one of them without [`Health`].
+fn setup() -> App {
+    let mut app = app(AutomaticUpdate::<Marker>::new());
+    for i in 0..10u8 {
+        let transform = Transform::from_xyz(f32::from(i), 0.0, 0.0);
+        let mut entity = app.world_mut().spawn((Marker, transform));
+        if i % 2 == 0 {
+            entity.insert(Health(u32::from(i)));
+        }
+    }
+    app.update();
+    app
+}
+
+fn run<T: 'static>(app: &mut App, f: impl Fn(Query) -> T + Send + Sync + 'static) -> T {
+    app.world_mut()
+        .run_system_once(move |query: Query| f(query))
+        .unwrap()
+}
+
+#[test]
+fn nearest_skips_entities_not_matching_the_query() {
+    let mut app = setup();
+    let nearest = run(&mut app, |query| {
+        query
+            .nearest(Vec3::new(2.9, 1.0, 0.0))
+            .map(|(distance, health)| (distance, health.0))
+    });
+    let (distance, health) = nearest.unwrap();
+    assert_eq!(health, 2);
+    assert!((distance - 1.81).abs() < 1e-4);
+}
+
+#[test]
+fn k_nearest_is_sorted_and_filtered() {
+    let mut app = setup();
+    let found = run(&mut app, |query| {
+        query
+            .k_nearest(Vec3::new(5.2, 0.0, 0.0), 3)
+            .into_iter()
+            .map(|(_, health)| health.0)
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(found, vec![6, 4, 8]);
+}
+
+#[test]
+fn within_distance_returns_the_squared_distances() {
+    let mut app = setup();
+    let mut found = run(&mut app, |query| {
+        query
+            .within_distance(Vec3::ZERO, 4.5)
+            .into_iter()
+            .map(|(distance, health)| (health.0, distance))
+            .collect::<Vec<_>>()
+    });
+    found.sort_by_key(|(health, _)| *health);
+    assert_eq!(found, vec![(0, 0.0), (2, 4.0), (4, 16.0)]);
+}
+
+#[test]
+fn entities_despawned_since_the_update_are_skipped() {
+    let mut app = setup();
+    let despawned = app
+        .world_mut()
+        .query::<(Entity, &Health)>()
+        .iter(app.world())
+        .find(|(_, health)| health.0 == 0)
+        .unwrap()
+        .0;
+    app.world_mut().despawn(despawned);
+
+    let (found, nearest) = run(&mut app, |query| {
+        let found: Vec<_> = query
+            .within_distance(Vec3::ZERO, 2.5)
+            .into_iter()
+            .map(|(_, health)| health.0)
+            .collect();
+        (found, query.nearest(Vec3::ZERO).map(|(_, health)| health.0))
+    });
+    assert_eq!(found, vec![2]);
+    assert_eq!(nearest, Some(2));
+}
@@ -0,0 +1,91 @@
+//! Checks that entities removed from the KD-trees are skipped by queries until the next rebuild drops them.
+
+mod common;
+
+use bevy::prelude::*;
+use bevy_spatial::{kdtree::KDTree2, SpatialAccess, SpatialPairAccess, UpdateSpatialAccess};
+use common::{entities, kdtree, random_points, sorted};
+
+#[derive(Component)]
+struct Marker;
+
+#[test]
+fn removed_entities_are_skipped_until_rebuild() {
+    let points = random_points(10, 200, 0);
+    let mut tree = kdtree::<Marker>(&points);
+    let removed: Vec<_> = (0..200).step_by(4).map(Entity::from_raw).collect();
+    for entity in &removed {
+        assert!(!tree.remove_entity(*entity));
+        assert!(tree.is_removed(*entity));
+    }
+    let live: Vec<_> = points
+        .iter()
+        .filter(|p| !removed.contains(&p.entity.unwrap()))
+        .copied()
+        .collect();
+
+    let check = |tree: &KDTree2<Marker>| {
+        for loc in [Vec2::ZERO, Vec2::splat(50.0), points[0].vec, points[4].vec] {
+            let nearest = live
+                .iter()
+                .min_by(|a, b| {
+                    a.vec
+                        .distance_squared(loc)
+                        .total_cmp(&b.vec.distance_squared(loc))
+                })
+                .unwrap();
+            assert_eq!(tree.nearest_neighbour(loc).unwrap().1, nearest.entity);
+
+            let within = live
+                .iter()
+                .filter(|p| p.vec.distance(loc) <= 15.0)
+                .filter_map(|p| p.entity)
+                .collect();
+            assert_eq!(entities(tree.within_distance(loc, 15.0)), sorted(within));
+
+            let found = tree.k_nearest_neighbour(loc, 20);
+            assert_eq!(found.len(), 20);
+            assert!(found.iter().all(|(_, e)| !removed.contains(&e.unwrap())));
+        }
+        let found = tree.pairs_within_distance(6.0);
+        assert!(found
+            .iter()
+            .all(|(a, b)| !removed.contains(&a.entity.unwrap())
+                && !removed.contains(&b.entity.unwrap())));
+    };
+    check(&tree);
+
+    // the next update rebuilds the tree without the removed entities
+    tree.update(live.iter().map(|p| (*p, false)), std::iter::empty());
+    assert!(tree.rebuilt_last_update());
+    assert!(removed.iter().all(|e| !tree.is_removed(*e)));
+    assert_eq!(tree.tree.len(), live.len());
+    check(&tree);
+}
+
+#[test]
+fn clear_forgets_removed_entities() {
+    let points = random_points(11, 50, 0);
+    let mut tree = kdtree::<Marker>(&points);
+    tree.remove_entity(Entity::from_raw(3));
+    tree.clear();
+    assert!(!tree.is_removed(Entity::from_raw(3)));
+    assert_eq!(tree.tree.len(), 0);
+    assert!(tree.nearest_neighbour(Vec2::ZERO).is_none());
+
+    // entities removed after clearing are skipped again, until the next rebuild drops them
+    tree.update(points.iter().map(|p| (*p, true)), std::iter::empty());
+    tree.remove_entity(Entity::from_raw(7));
+    assert!(tree.is_removed(Entity::from_raw(7)));
+    let found = tree.within_distance(Vec2::ZERO, 2.0 * common::SIZE);
+    assert_eq!(found.len(), points.len() - 1);
+    tree.update(
+        points
+            .iter()
+            .filter(|p| p.entity != Some(Entity::from_raw(7)))
+            .map(|p| (*p, false)),
+        std::iter::empty(),
+    );
+    assert!(!tree.is_removed(Entity::from_raw(7)));
+    assert_eq!(tree.tree.len(), points.len() - 1);
+}
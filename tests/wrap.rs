@@ -0,0 +1,242 @@
+//! Checks the queries of wrapped KD-trees against brute force over the copies of the world.
+
+mod common;
+
+use bevy::{math::DVec3, prelude::*};
+use bevy_spatial::{
+    kdtree::{KDTree2, KDTreeD2},
+    linear::Linear2,
+    point::{Point2, SpatialPoint},
+    AutomaticUpdate, SpatialAABBAccess, SpatialAccess, SpatialPairAccess, SpatialRayAccess,
+    SpatialStructure, UpdateSpatialAccess,
+};
+use common::{app, entities, kdtree, pairs, random_points, sorted, SIZE};
+
+#[derive(Component)]
+struct Marker;
+
+#[derive(Component)]
+struct Other;
+
+fn wrapped<Comp: Component>(points: &[Point2]) -> KDTree2<Comp> {
+    let mut tree = KDTree2::default().with_wrap(Vec2::splat(SIZE));
+    tree.update(points.iter().map(|p| (*p, true)), std::iter::empty());
+    tree
+}
+
+/// Squared distance between `a` and `b` on the wrapped world, going across the edges where that is shorter.
+fn wrapped_distance_squared(a: Vec2, b: Vec2) -> f32 {
+    let d = (a - b).abs();
+    d.min(Vec2::splat(SIZE) - d).length_squared()
+}
+
+#[test]
+fn wrap_finds_points_across_edges() {
+    let points = random_points(1, 300, 0);
+    let tree = wrapped::<Marker>(&points);
+    let queries = [
+        Vec2::ZERO,
+        Vec2::new(1.0, 99.0),
+        Vec2::new(50.0, 0.5),
+        Vec2::new(98.0, 42.0),
+        points[17].vec,
+    ];
+    for loc in queries {
+        let mut by_distance: Vec<_> = points
+            .iter()
+            .map(|p| (wrapped_distance_squared(loc, p.vec), p.entity.unwrap()))
+            .collect();
+        by_distance.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let (distance, nearest) = tree.nearest_neighbour_with_distance(loc).unwrap();
+        assert!((distance - by_distance[0].0).abs() < 1e-3);
+        assert_eq!(nearest.1, Some(by_distance[0].1));
+
+        let found = tree.k_nearest_neighbour_with_distance(loc, 10);
+        for ((found, _), (expected, _)) in found.iter().zip(&by_distance[..10]) {
+            assert!((found - expected).abs() < 1e-3);
+        }
+
+        let radius = 12.0;
+        let expected = by_distance
+            .iter()
+            .filter(|(d, _)| *d <= radius * radius)
+            .map(|(_, e)| *e)
+            .collect();
+        assert_eq!(
+            entities(tree.within_distance(loc, radius)),
+            sorted(expected)
+        );
+    }
+}
+
+#[test]
+fn wrap_aabb_crosses_edges() {
+    let points = random_points(2, 300, 0);
+    let tree = wrapped::<Marker>(&points);
+    let (min, max) = (Vec2::new(-10.0, 85.0), Vec2::new(8.0, 112.0));
+    let expected = points
+        .iter()
+        .filter(|p| {
+            [-SIZE, 0.0, SIZE].iter().any(|x| {
+                [-SIZE, 0.0, SIZE].iter().any(|y| {
+                    let v = p.vec + Vec2::new(*x, *y);
+                    v.cmpge(min).all() && v.cmple(max).all()
+                })
+            })
+        })
+        .filter_map(SpatialPoint::entity)
+        .collect();
+    assert_eq!(entities(tree.within_aabb(min, max)), sorted(expected));
+}
+
+#[test]
+fn wrap_pairs_and_join() {
+    let points = random_points(3, 200, 0);
+    let others = random_points(4, 100, 1000);
+    let tree = wrapped::<Marker>(&points);
+    let other_tree = wrapped::<Other>(&others);
+    let radius: f32 = 7.0;
+
+    let mut expected = vec![];
+    for (i, a) in points.iter().enumerate() {
+        for b in &points[i + 1..] {
+            if wrapped_distance_squared(a.vec, b.vec) <= radius * radius {
+                expected.push((a.entity.unwrap(), b.entity.unwrap()));
+            }
+        }
+    }
+    let expected = pairs(expected);
+    let found = |found: Vec<(Point2, Point2)>| {
+        pairs(
+            found
+                .into_iter()
+                .map(|(a, b)| (a.entity.unwrap(), b.entity.unwrap())),
+        )
+    };
+    assert_eq!(found(tree.pairs_within_distance(radius)), expected);
+    assert_eq!(found(tree.pairs_within_distance_par(radius)), expected);
+
+    let mut expected = vec![];
+    for a in &points {
+        for b in &others {
+            if wrapped_distance_squared(a.vec, b.vec) <= radius * radius {
+                expected.push((a.entity.unwrap(), b.entity.unwrap()));
+            }
+        }
+    }
+    let mut found = vec![];
+    tree.dual_join_within_distance(&other_tree, radius, |a, b| {
+        found.push((a.entity.unwrap(), b.entity.unwrap()));
+    });
+    assert_eq!(sorted(found), sorted(expected));
+}
+
+#[test]
+fn wrap_ray_casts_cross_edges() {
+    let points = random_points(5, 200, 0);
+    let tree = wrapped::<Marker>(&points);
+    // the copies of the world a ray of up to 150 starting inside of it can reach
+    let mut copies = Linear2::<Marker>::default();
+    let offsets: Vec<_> = (-2..=2)
+        .flat_map(|x| (-2..=2).map(move |y| Vec2::new(x as f32, y as f32) * SIZE))
+        .collect();
+    copies.update(
+        points.iter().flat_map(|p| {
+            offsets
+                .iter()
+                .map(|o| (Point2::from((p.vec + *o, p.entity.unwrap())), true))
+        }),
+        std::iter::empty(),
+    );
+
+    let rays = [
+        (Vec2::new(50.0, 50.0), Vec2::X, 150.0),
+        (
+            Vec2::new(3.0, 97.0),
+            Vec2::new(-1.0, 1.0).normalize(),
+            120.0,
+        ),
+        (
+            Vec2::new(-20.0, 10.0),
+            Vec2::new(0.3, -1.0).normalize(),
+            90.0,
+        ),
+        (Vec2::new(99.0, 1.0), Vec2::new(1.0, 0.1).normalize(), 5.0),
+    ];
+    for (origin, direction, max_t) in rays {
+        for radius in [0.5, 3.0] {
+            let found = tree.cast_ray(origin, direction, max_t, radius);
+            let expected = copies.cast_ray(origin, direction, max_t, radius);
+            assert!(found.windows(2).all(|w| w[0].0 <= w[1].0));
+            assert!(found
+                .iter()
+                .all(|(_, (vec, _))| vec.cmpge(Vec2::ZERO).all()
+                    && vec.cmplt(Vec2::splat(SIZE)).all()));
+            let hits = |hits: &[(f32, (Vec2, Option<Entity>))]| {
+                sorted(hits.iter().map(|(_, (_, e))| e.unwrap()).collect())
+            };
+            assert_eq!(hits(&found), hits(&expected));
+            for ((a, _), (b, _)) in found.iter().zip(&expected) {
+                assert!((a - b).abs() < 1e-3);
+            }
+        }
+    }
+
+    // a ray of infinite length would go around the world forever
+    assert!(tree
+        .cast_ray(Vec2::ZERO, Vec2::X, f32::INFINITY, 1.0)
+        .is_empty());
+}
+
+#[test]
+fn dual_join_measures_distances_across_the_edges_of_the_other_tree() {
+    let points = random_points(6, 200, 0);
+    let others = random_points(7, 100, 1000);
+    let radius: f32 = 7.0;
+    let join = |a: &KDTree2<Marker>, b: &KDTree2<Other>| {
+        let mut found = vec![];
+        a.dual_join_within_distance(b, radius, |a, b| {
+            found.push((a.entity.unwrap(), b.entity.unwrap()));
+        });
+        sorted(found)
+    };
+    let expected = |distance_squared: fn(Vec2, Vec2) -> f32| {
+        let mut expected = vec![];
+        for a in &points {
+            for b in &others {
+                if distance_squared(a.vec, b.vec) <= radius * radius {
+                    expected.push((a.entity.unwrap(), b.entity.unwrap()));
+                }
+            }
+        }
+        sorted(expected)
+    };
+
+    assert_eq!(
+        join(&wrapped(&points), &kdtree(&others)),
+        expected(Vec2::distance_squared)
+    );
+    assert_eq!(
+        join(&kdtree(&points), &wrapped(&others)),
+        expected(wrapped_distance_squared)
+    );
+}
+
+#[test]
+fn automatic_update_wraps_in_double_precision() {
+    let extents = DVec3::new(1e9 + 0.5, 3.25, 0.0);
+    let app = app(AutomaticUpdate::<Marker>::new()
+        .with_spatial_ds(SpatialStructure::KDTreeD2)
+        .with_wrap(extents));
+    let tree = app.world().resource::<KDTreeD2<Marker>>();
+    assert_eq!(tree.wrap(), Some(extents.truncate()));
+}
+
+#[test]
+#[should_panic(expected = "AutomaticUpdate::with_wrap only works with the KD-trees")]
+fn automatic_update_rejects_wrap_for_other_datastructures() {
+    let _ = app(AutomaticUpdate::<Marker>::new()
+        .with_spatial_ds(SpatialStructure::Grid2 { cell_size: 10.0 })
+        .with_wrap(DVec3::splat(100.0)));
+}